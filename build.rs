@@ -25,6 +25,21 @@ fn get_git_hash() -> String {
     }
 }
 
+/// Reads the public key for verifying release manifests from `assets/release-key.pub`, as 64 hex digits.
+///
+/// Returns `None` if the file doesn't exist, in which case the built executable can't verify self-updates and doesn't check for them.
+fn get_release_public_key() -> Result<Option<Vec<u8>>, io::Error> {
+    let mut hex = String::default();
+    match File::open("assets/release-key.pub") {
+        Ok(mut f) => { f.read_to_string(&mut hex)?; }
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => { return Ok(None); }
+        Err(e) => { return Err(e); }
+    }
+    let hex = hex.trim();
+    if hex.len() != 64 { panic!("assets/release-key.pub must contain exactly 64 hex digits"); }
+    Ok(Some((0..32).map(|i| u8::from_str_radix(&hex[2 * i..2 * i + 2], 16).expect("invalid hex digit in assets/release-key.pub")).collect()))
+}
+
 fn main() -> Result<(), io::Error> {
     println!("cargo:rerun-if-changed=nonexistent.foo"); // check a nonexistent file to make sure build script is always run (see https://github.com/rust-lang/cargo/issues/4213)
     {
@@ -33,6 +48,12 @@ fn main() -> Result<(), io::Error> {
         writeln!(f, "")?;
         writeln!(f, "/// The hash of the current commit of the lore-seeker-desktop repo at compile time.")?;
         writeln!(f, "pub const GIT_COMMIT_HASH: &str = \"{}\";", get_git_hash())?;
+        writeln!(f, "")?;
+        writeln!(f, "/// The ed25519 public key used to verify signed release manifests, read from `assets/release-key.pub` at compile time.")?;
+        match get_release_public_key()? {
+            Some(key) => { writeln!(f, "pub const RELEASE_PUBLIC_KEY: Option<[u8; 32]> = Some({:?});", key)?; }
+            None => { writeln!(f, "pub const RELEASE_PUBLIC_KEY: Option<[u8; 32]> = None;")?; }
+        }
     } // close src/version.rs
     #[cfg(windows)] {
         let mut res = WindowsResource::new();
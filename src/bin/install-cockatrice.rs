@@ -2,8 +2,64 @@
 #![deny(unused, unused_qualifications)]
 #![forbid(unused_import_braces)]
 
+use std::{
+    env,
+    ffi::OsString,
+    fmt,
+    path::PathBuf
+};
+use wrapped_enum::wrapped_enum;
 use lore_seeker_desktop::trice;
 
-fn main() -> Result<(), trice::Error> {
-    trice::install(true)
+#[derive(Debug)]
+enum ArgsError {
+    MissingValue(&'static str),
+    Unknown(OsString)
+}
+
+impl fmt::Display for ArgsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ArgsError::MissingValue(arg) => write!(f, "missing value for {}", arg),
+            ArgsError::Unknown(ref arg) => write!(f, "unknown argument: {}", arg.to_string_lossy())
+        }
+    }
+}
+
+wrapped_enum! {
+    #[derive(Debug)]
+    enum Error {
+        Args(ArgsError),
+        Trice(trice::Error)
+    }
+}
+
+static USAGE: &'static str = "usage: install-cockatrice [--silent] [--allow-unverified] [--target-dir <path>] [--prerelease | --tag <tag>]";
+
+fn parse_args() -> Result<trice::InstallOptions, ArgsError> {
+    let mut options = trice::InstallOptions {
+        debug: true,
+        ..trice::InstallOptions::default()
+    };
+    let mut args = env::args_os().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.to_str() {
+            Some("--silent") | Some("-s") => { options.silent = true; }
+            Some("--allow-unverified") => { options.allow_unverified = true; }
+            Some("--target-dir") => { options.target_dir = Some(PathBuf::from(args.next().ok_or(ArgsError::MissingValue("--target-dir"))?)); }
+            Some("--prerelease") => { options.version = trice::InstallVersion::LatestPrerelease; }
+            Some("--tag") => { options.version = trice::InstallVersion::Tag(args.next().ok_or(ArgsError::MissingValue("--tag"))?.to_string_lossy().into_owned()); }
+            _ => { return Err(ArgsError::Unknown(arg)); }
+        }
+    }
+    Ok(options)
+}
+
+fn main() -> Result<(), Error> {
+    let options = parse_args().map_err(|e| {
+        eprintln!("{}\n{}", e, USAGE);
+        e
+    })?;
+    trice::install(&options, |_, _| ())?; // progress is already printed because `debug` is set
+    Ok(())
 }
@@ -4,7 +4,12 @@
 
 use std::{
     cmp::Ordering::*,
-    fs::File,
+    collections::BTreeMap,
+    env,
+    fs::{
+        self,
+        File
+    },
     io::{
         self,
         prelude::*
@@ -17,16 +22,35 @@ use semver::{
     Version
 };
 use wrapped_enum::wrapped_enum;
+use ed25519_dalek::SigningKey;
 use lore_seeker_desktop::{
+    checksum::{
+        self,
+        HashingWriter,
+        Manifest
+    },
+    delta,
     github::Repo,
-    util
+    signing::{
+        self,
+        ReleaseManifest
+    },
+    update::NIGHTLY_TAG,
+    util,
+    version::{
+        GIT_COMMIT_HASH,
+        RELEASE_PUBLIC_KEY
+    }
 };
 
 #[derive(Debug)]
 enum OtherError {
     Command,
+    InvalidReleaseKey,
     MissingPackage,
+    PublicKeyMismatch,
     SameVersion,
+    Usage,
     VersionRegression
 }
 
@@ -35,6 +59,7 @@ wrapped_enum! {
     enum Error {
         Cargo(cargo_metadata::Error),
         Io(io::Error),
+        Json(serde_json::Error),
         Other(OtherError),
         ReleaseClient(util::ReleaseClientError),
         Reqwest(reqwest::Error),
@@ -42,24 +67,72 @@ wrapped_enum! {
     }
 }
 
+/// The release assets for each platform, and where the new build of each is.
+static PLATFORM_ASSETS: [(&'static str, &'static str); 2] = [
+    ("lore-seeker-windows-64bit.exe", "target/release/lore-seeker-desktop.exe"),
+    ("lore-seeker-windows-32bit.exe", "target-x86/release/lore-seeker-desktop.exe")
+];
+
+/// Creates patches from the platform assets of the previous `delta::PREVIOUS_VERSIONS` releases to the new builds, keyed by asset name.
+///
+/// Previous releases whose assets don't match their published checksums are skipped.
+fn patches(client: &reqwest::Client, repo: &Repo, local_version: &Version) -> Result<Vec<(String, Vec<u8>)>, Error> {
+    let mut patches = Vec::default();
+    let previous_releases = repo.releases(client)?
+        .into_iter()
+        .filter(|release| !release.draft && release.tag_name.starts_with('v'))
+        .filter_map(|release| Some((release.tag_name[1..].parse::<Version>().ok()?, release)))
+        .filter(|(version, _)| version < local_version)
+        .take(delta::PREVIOUS_VERSIONS);
+    for (version, release) in previous_releases {
+        let old_manifest = Manifest::fetch(client, &release)?;
+        for &(asset_name, new_path) in &PLATFORM_ASSETS {
+            let asset = match release.assets.iter().find(|asset| asset.name == asset_name) {
+                Some(asset) => asset,
+                None => { continue; }
+            };
+            let mut writer = HashingWriter::new(Vec::default());
+            client.get(&asset.browser_download_url).send()?.error_for_status()?.copy_to(&mut writer)?;
+            let (old, digest) = writer.finish();
+            if old_manifest.as_ref().and_then(|manifest| manifest.get(asset_name)).map_or(false, |expected| expected != digest) { continue; }
+            patches.push((delta::patch_name(asset_name, &version), delta::diff(&old, &fs::read(new_path)?)?));
+        }
+    }
+    Ok(patches)
+}
+
+/// With `--nightly`, the build replaces the release tagged `nightly` instead of being published as a new version. Nightly builds don't need a version bump and don't include patches.
 fn main() -> Result<(), Error> {
     //TODO make sure working dir is clean and on master and up to date with remote and remote is up to date. Alternatively, make sure we're on gitdir master and up to date
+    let nightly = match env::args_os().nth(1) {
+        None => false,
+        Some(ref arg) if arg == "--nightly" => true,
+        Some(arg) => {
+            eprintln!("unknown argument: {:?}\nusage: lore-seeker-release [--nightly]", arg);
+            return Err(OtherError::Usage.into());
+        }
+    };
     let repo = Repo::new("fenhl", "lore-seeker-desktop");
     let client = util::release_client()?;
+    let release_key = SigningKey::from_bytes(&signing::key_from_hex(&fs::read_to_string("assets/release-key")?).ok_or(OtherError::InvalidReleaseKey)?);
+    // the release builds embed assets/release-key.pub, so make sure they'll be able to verify this and future releases
+    if RELEASE_PUBLIC_KEY != Some(release_key.verifying_key().to_bytes()) { return Err(OtherError::PublicKeyMismatch.into()); }
     let metadata = cargo_metadata::MetadataCommand::default().exec()?;
     let (pkg,) = metadata.packages.into_iter().filter(|pkg| pkg.name == "lore-seeker").collect_tuple().ok_or(OtherError::MissingPackage)?;
     let local_version = pkg.version;
     let remote_version = repo.latest_release(&client)?.tag_name[1..].parse::<Version>()?;
     match local_version.cmp(&remote_version) {
         Less => { return Err(OtherError::VersionRegression.into()); }
-        Equal => { return Err(OtherError::SameVersion.into()); }
-        Greater => {}
+        Equal if !nightly => { return Err(OtherError::SameVersion.into()); }
+        Equal | Greater => {}
     }
     if !Command::new("rustup").arg("update").arg("stable").status()?.success() { return Err(OtherError::Command.into()); }
     if !Command::new("rustup").arg("update").arg("stable-i686-pc-windows-msvc").status()?.success() { return Err(OtherError::Command.into()); }
     if !Command::new("cargo").arg("build").arg("--bin=lore-seeker-desktop").arg("--release").status()?.success() { return Err(OtherError::Command.into()); }
     if !Command::new("cargo").arg("+stable-i686-pc-windows-msvc").arg("build").arg("--bin=lore-seeker-desktop").arg("--release").arg("--target-dir=target-x86").status()?.success() { return Err(OtherError::Command.into()); }
-    let release_notes = {
+    let release_notes = if nightly {
+        format!("Automatically built from commit {}.", GIT_COMMIT_HASH)
+    } else {
         let mut release_notes_file = tempfile::Builder::new()
             .prefix("lore-seeker-desktop-release-notes")
             .suffix(".md")
@@ -69,9 +142,43 @@ fn main() -> Result<(), Error> {
         release_notes_file.read_to_string(&mut buf)?;
         buf
     };
-    let release = repo.create_release(&client, format!("Lore Seeker Desktop {}", local_version), format!("v{}", local_version), release_notes)?;
-    repo.release_attach(&client, &release, "lore-seeker-windows-64bit.exe", "application/vnd.microsoft.portable-executable", File::open("target/release/lore-seeker-desktop.exe")?)?;
-    repo.release_attach(&client, &release, "lore-seeker-windows-32bit.exe", "application/vnd.microsoft.portable-executable", File::open("target-x86/release/lore-seeker-desktop.exe")?)?;
+    let mut manifest = Manifest::default();
+    let mut signed_assets = BTreeMap::default();
+    for &(name, path) in &PLATFORM_ASSETS {
+        let digest = checksum::file_digest(path)?;
+        manifest.insert(name, &digest);
+        signed_assets.insert(name.to_owned(), digest);
+    }
+    let patches = if nightly { Vec::default() } else { patches(&client, &repo, &local_version)? };
+    for (name, patch) in &patches {
+        let mut writer = HashingWriter::new(io::sink());
+        writer.write_all(patch)?;
+        let digest = writer.finish().1;
+        manifest.insert(name, &digest);
+        signed_assets.insert(name.clone(), digest);
+    }
+    let (signed_manifest, signature) = ReleaseManifest {
+        version: local_version.to_string(),
+        commit: GIT_COMMIT_HASH.to_owned(),
+        assets: signed_assets
+    }.sign(&release_key)?;
+    let release = if nightly {
+        // the tag has to be deleted along with the release so the new release can move it to the current commit
+        if let Some(previous) = repo.release_by_tag(&client, NIGHTLY_TAG)? { repo.delete_release(&client, previous)?; }
+        repo.delete_tag(&client, NIGHTLY_TAG)?;
+        repo.create_release(&client, format!("Lore Seeker Desktop nightly ({})", &GIT_COMMIT_HASH[..7]), NIGHTLY_TAG.to_owned(), GIT_COMMIT_HASH, true, release_notes)?
+    } else {
+        repo.create_release(&client, format!("Lore Seeker Desktop {}", local_version), format!("v{}", local_version), GIT_COMMIT_HASH, false, release_notes)?
+    };
+    for &(name, path) in &PLATFORM_ASSETS {
+        repo.release_attach(&client, &release, name, "application/vnd.microsoft.portable-executable", File::open(path)?)?;
+    }
+    for (name, patch) in patches {
+        repo.release_attach(&client, &release, &name, "application/gzip", patch)?;
+    }
+    repo.release_attach(&client, &release, checksum::MANIFEST_NAME, "text/plain", manifest.to_string())?;
+    repo.release_attach(&client, &release, signing::MANIFEST_NAME, "application/json", signed_manifest)?;
+    repo.release_attach(&client, &release, signing::SIGNATURE_NAME, "application/octet-stream", signature)?;
     repo.publish_release(&client, release)?;
     Ok(())
 }
@@ -0,0 +1,156 @@
+#![warn(trivial_casts)]
+#![deny(unused, unused_qualifications)]
+#![forbid(unused_import_braces)]
+
+use std::{
+    env,
+    fmt
+};
+use wrapped_enum::wrapped_enum;
+use lore_seeker_desktop::{
+    config::{
+        self,
+        Config
+    },
+    history::{
+        self,
+        History
+    },
+    lore_seeker::SearchPage,
+    query,
+    search::Index,
+    trice::carddb,
+    util
+};
+
+#[derive(Debug)]
+enum ArgsError {
+    InvalidPage(String),
+    MissingValue(&'static str),
+    NoSuchSavedSearch(String),
+    NotSynced,
+    Query(query::ParseError)
+}
+
+impl fmt::Display for ArgsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ArgsError::InvalidPage(ref page) => write!(f, "invalid page number {:?}, pages start at 1", page),
+            ArgsError::MissingValue(arg) => write!(f, "missing value for {}", arg),
+            ArgsError::NoSuchSavedSearch(ref name) => write!(f, "no saved search named {:?}", name),
+            ArgsError::NotSynced => write!(f, "no synced card database found, run Lore Seeker Desktop to sync one"),
+            ArgsError::Query(ref e) => write!(f, "invalid query: {}", e)
+        }
+    }
+}
+
+wrapped_enum! {
+    #[derive(Debug)]
+    enum Error {
+        Args(ArgsError),
+        CardDb(carddb::Error),
+        Config(config::Error),
+        History(history::Error),
+        Reqwest(reqwest::Error)
+    }
+}
+
+static USAGE: &'static str = "usage:
+  lore-seeker-search [--offline] [--page <n>] <query>...
+  lore-seeker-search [--offline] [--page <n>] --run <name>
+  lore-seeker-search --save <name> <query>...
+  lore-seeker-search --forget <name>
+  lore-seeker-search --history
+  lore-seeker-search --saved
+  lore-seeker-search --clear-history";
+
+fn print_page(page: &SearchPage) {
+    for card in &page.cards {
+        print!("{}", card.name);
+        if let Some(ref mana_cost) = card.mana_cost { print!(" {}", mana_cost); }
+        print!(" — {} ({}", card.type_line, card.set.to_uppercase());
+        if let Some(ref number) = card.number { print!(" #{}", number); }
+        println!(")");
+    }
+    println!("page {} of {} ({} cards)", page.page, page.total_pages(), page.total_cards);
+}
+
+fn search(config: &Config, history: &mut History, query: &str, page: usize, offline: bool) -> Result<(), Error> {
+    let results = if offline {
+        let parsed = query::parse(query).map_err(ArgsError::Query)?;
+        Index::open(&config.cockatrice_paths().ok_or(ArgsError::NotSynced)?)?.search_page(&parsed, page)
+    } else {
+        if let Err(e) = query::parse(query) {
+            if e.is_syntax_error() { return Err(ArgsError::Query(e).into()); }
+        }
+        config.server.search(&util::client()?, query, page)?
+    };
+    print_page(&results);
+    history.record(query);
+    history.save()?;
+    Ok(())
+}
+
+fn run() -> Result<(), Error> {
+    let config = Config::load()?;
+    let mut history = History::load()?;
+    let mut offline = false;
+    let mut page = 1;
+    let mut args = env::args().skip(1).peekable();
+    loop {
+        match args.peek().map(String::as_str) {
+            Some("--offline") => { args.next(); offline = true; }
+            Some("--page") => {
+                args.next();
+                let arg = args.next().ok_or(ArgsError::MissingValue("--page"))?;
+                page = match arg.parse() {
+                    Ok(0) | Err(_) => { return Err(ArgsError::InvalidPage(arg).into()); }
+                    Ok(page) => page
+                };
+            }
+            _ => { break; }
+        }
+    }
+    match args.peek().map(String::as_str) {
+        Some("--history") => {
+            for query in &history.recent { println!("{}", query); }
+            Ok(())
+        }
+        Some("--saved") => {
+            for (name, query) in &history.saved { println!("{}: {}", name, query); }
+            Ok(())
+        }
+        Some("--clear-history") => {
+            history.recent.clear();
+            history.save().map_err(Error::from)
+        }
+        Some("--save") => {
+            args.next();
+            let name = args.next().ok_or(ArgsError::MissingValue("--save"))?;
+            history.save_search(name, &args.collect::<Vec<_>>().join(" "));
+            history.save().map_err(Error::from)
+        }
+        Some("--forget") => {
+            args.next();
+            let name = args.next().ok_or(ArgsError::MissingValue("--forget"))?;
+            if !history.forget(&name) { return Err(ArgsError::NoSuchSavedSearch(name).into()); }
+            history.save().map_err(Error::from)
+        }
+        Some("--run") => {
+            args.next();
+            let name = args.next().ok_or(ArgsError::MissingValue("--run"))?;
+            let query = history.saved.get(&name).cloned().ok_or(ArgsError::NoSuchSavedSearch(name))?;
+            search(&config, &mut history, &query, page, offline)
+        }
+        _ => {
+            let query = args.collect::<Vec<_>>().join(" ");
+            search(&config, &mut history, &query, page, offline)
+        }
+    }
+}
+
+fn main() -> Result<(), Error> {
+    let result = run();
+    if let Err(Error::Args(ref e)) = result { eprintln!("{}\n{}", e, USAGE); }
+    result
+}
@@ -0,0 +1,159 @@
+//! SHA-256 checksums for release assets.
+
+use std::{
+    collections::BTreeMap,
+    fmt,
+    fs::File,
+    io::{
+        self,
+        prelude::*
+    },
+    path::Path
+};
+use sha2::{
+    Digest,
+    Sha256
+};
+use crate::github::Release;
+
+/// The name of the release asset listing the SHA-256 checksums of the other assets, in the format used by `sha256sum`.
+pub static MANIFEST_NAME: &'static str = "SHA256SUMS";
+
+/// A list of SHA-256 checksums, keyed by file name.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Manifest {
+    entries: BTreeMap<String, String>
+}
+
+impl Manifest {
+    /// Parses a manifest in the format used by `sha256sum`, i.e. one line per file containing the hex digest, whitespace, and the file name, optionally prefixed with `*`.
+    ///
+    /// Lines which don't match this format are ignored.
+    pub fn parse(text: &str) -> Manifest {
+        Manifest {
+            entries: text.lines().filter_map(|line| {
+                let mut parts = line.trim().splitn(2, char::is_whitespace);
+                let digest = parts.next()?;
+                let name = parts.next()?.trim_start().trim_start_matches('*');
+                if digest.len() != 64 || !digest.chars().all(|c| c.is_ascii_hexdigit()) || name.is_empty() { return None; }
+                Some((name.to_owned(), digest.to_ascii_lowercase()))
+            }).collect()
+        }
+    }
+
+    /// Downloads and parses the checksum manifest attached to the given release, if any.
+    pub fn fetch(client: &reqwest::Client, release: &Release) -> Result<Option<Manifest>, reqwest::Error> {
+        let asset = match release.assets.iter().find(|asset| asset.name == MANIFEST_NAME) {
+            Some(asset) => asset,
+            None => { return Ok(None); }
+        };
+        Ok(Some(Manifest::parse(&client.get(&asset.browser_download_url).send()?.error_for_status()?.text()?)))
+    }
+
+    /// Returns the hex digest listed for the given file name.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.entries.get(name).map(String::as_str)
+    }
+
+    /// Adds or replaces the digest for the given file name.
+    pub fn insert(&mut self, name: impl ToString, digest: impl ToString) {
+        self.entries.insert(name.to_string(), digest.to_string().to_ascii_lowercase());
+    }
+}
+
+impl fmt::Display for Manifest {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (name, digest) in &self.entries {
+            writeln!(f, "{}  {}", digest, name)?;
+        }
+        Ok(())
+    }
+}
+
+/// A writer which computes the SHA-256 digest of everything written to it.
+pub struct HashingWriter<W: Write> {
+    inner: W,
+    hasher: Sha256
+}
+
+impl<W: Write> HashingWriter<W> {
+    /// Wraps the given writer.
+    pub fn new(inner: W) -> HashingWriter<W> {
+        HashingWriter {
+            inner,
+            hasher: Sha256::new()
+        }
+    }
+
+    /// Returns the underlying writer and the lowercase hex digest of the data written.
+    pub fn finish(self) -> (W, String) {
+        (self.inner, hex(&self.hasher.finalize()))
+    }
+}
+
+impl<W: Write> Write for HashingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.hasher.update(&buf[..written]);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Returns the lowercase hex SHA-256 digest of the file at the given path.
+pub fn file_digest(path: impl AsRef<Path>) -> io::Result<String> {
+    let mut writer = HashingWriter::new(io::sink());
+    io::copy(&mut File::open(path)?, &mut writer)?;
+    Ok(writer.finish().1)
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static DIGEST: &'static str = "9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08";
+
+    #[test]
+    fn parse() {
+        for &(text, expected) in &[
+            // well-formed lines, in text and binary mode
+            ("9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08  Cockatrice.AppImage\n", Some("Cockatrice.AppImage")),
+            ("9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08 *Cockatrice.AppImage", Some("Cockatrice.AppImage")),
+            ("9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08\tCockatrice-win64.exe\r\n", Some("Cockatrice-win64.exe")),
+            ("9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08  Cockatrice setup.exe", Some("Cockatrice setup.exe")),
+            // uppercase digests are normalized
+            ("9F86D081884C7D659A2FEAA0C55AD015A3BF4F1B2B0B822CD15D6C15B0F00A08  Cockatrice.AppImage", Some("Cockatrice.AppImage")),
+            // malformed lines
+            ("", None),
+            ("9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08", None),
+            ("9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08  ", None),
+            ("9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08  *", None),
+            ("9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a0  Cockatrice.AppImage", None),
+            ("9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a088  Cockatrice.AppImage", None),
+            ("zf86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08  Cockatrice.AppImage", None),
+            ("Cockatrice.AppImage  9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08", None)
+        ] {
+            let manifest = Manifest::parse(text);
+            let mut names = manifest.entries.keys().map(String::as_str);
+            assert_eq!(names.next(), expected, "parsing {:?}", text);
+            assert_eq!(names.next(), None, "parsing {:?}", text);
+            if let Some(name) = expected { assert_eq!(manifest.get(name), Some(DIGEST)); }
+        }
+    }
+
+    #[test]
+    fn parse_skips_malformed_lines() {
+        let manifest = Manifest::parse(&format!("# checksums\n{}  a.exe\nnot a checksum\n\n{} *b.AppImage\n", DIGEST, DIGEST.to_ascii_uppercase()));
+        assert_eq!(manifest.get("a.exe"), Some(DIGEST));
+        assert_eq!(manifest.get("b.AppImage"), Some(DIGEST));
+        assert_eq!(manifest.entries.len(), 2);
+        assert_eq!(Manifest::parse(&manifest.to_string()), manifest);
+    }
+}
@@ -0,0 +1,252 @@
+//! The settings file of Lore Seeker Desktop.
+
+use std::{
+    fmt,
+    fs,
+    io,
+    path::PathBuf
+};
+use semver::Version;
+use serde_derive::{
+    Deserialize,
+    Serialize
+};
+use serde_json::{
+    Value,
+    json
+};
+use wrapped_enum::wrapped_enum;
+use crate::{
+    github::Repo,
+    lore_seeker::Server,
+    trice::{
+        self,
+        paths::Paths
+    },
+    update::UpdateChannel,
+    util
+};
+
+/// The version of the settings file format written by this version of Lore Seeker Desktop.
+///
+/// Version 0 refers to the plain text files which were used to store individual settings before the settings file was introduced.
+pub const CURRENT_VERSION: u64 = 2;
+
+/// The shortest allowed interval between update checks and syncs, in seconds. Shorter intervals in the settings file are raised to this when loading.
+pub const MIN_UPDATE_INTERVAL_SECS: u64 = 60;
+
+/// The file listing skipped versions before the settings file was introduced.
+static LEGACY_SKIPPED_VERSIONS: &'static str = "skipped-versions.txt";
+/// The file storing the update channel before the settings file was introduced.
+static LEGACY_UPDATE_CHANNEL: &'static str = "update-channel.txt";
+
+/// An error that can occur while loading or saving the settings.
+#[derive(Debug)]
+pub enum OtherError {
+    /// The platform's config directory could not be determined.
+    MissingConfigDir,
+    /// The settings file was written by a newer version of Lore Seeker Desktop.
+    UnsupportedVersion(u64)
+}
+
+wrapped_enum! {
+    /// An error that can occur while loading or saving the settings.
+    #[derive(Debug)]
+    pub enum Error {
+        #[allow(missing_docs)]
+        Io(io::Error),
+        #[allow(missing_docs)]
+        Json(serde_json::Error),
+        #[allow(missing_docs)]
+        Other(OtherError)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::Io(ref e) => e.fmt(f),
+            Error::Json(ref e) => write!(f, "The settings file is invalid: {}", e),
+            Error::Other(OtherError::MissingConfigDir) => write!(f, "Could not find the config directory."),
+            Error::Other(OtherError::UnsupportedVersion(version)) => write!(f, "The settings file was saved by a newer version of Lore Seeker Desktop (format version {}).", version)
+        }
+    }
+}
+
+/// The user's settings. Missing fields are filled in with their defaults when loading.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// The version of the settings file format, see `CURRENT_VERSION`.
+    pub version: u64,
+    /// The Lore Seeker server used for searches and syncing.
+    pub server: Server,
+    /// How often to check for updates and sync Cockatrice files, in seconds. At least `MIN_UPDATE_INTERVAL_SECS`.
+    pub update_interval_secs: u64,
+    /// Which kinds of releases to offer as updates.
+    pub update_channel: UpdateChannel,
+    /// The repository Lore Seeker Desktop updates are downloaded from.
+    pub update_repo: Repo,
+    /// The repository Cockatrice is installed from.
+    pub cockatrice_repo: Repo,
+    /// The Cockatrice data directory to sync to, if Cockatrice's own data directory lookup should be overridden.
+    pub cockatrice_data_dir: Option<PathBuf>,
+    /// Where to install Cockatrice, if not in the default location.
+    pub cockatrice_install_dir: Option<PathBuf>,
+    /// Versions of Lore Seeker Desktop the user chose not to be offered.
+    pub skipped_versions: Vec<Version>,
+    /// Commit hashes of nightly builds the user chose not to be offered. Nightly builds are skipped by commit since they share a version number.
+    pub skipped_commits: Vec<String>
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            version: CURRENT_VERSION,
+            server: Server::default(),
+            update_interval_secs: 3600,
+            update_channel: UpdateChannel::default(),
+            update_repo: Repo::new("fenhl", "lore-seeker-desktop"),
+            cockatrice_repo: trice::default_repo(),
+            cockatrice_data_dir: None,
+            cockatrice_install_dir: None,
+            skipped_versions: Vec::default(),
+            skipped_commits: Vec::default()
+        }
+    }
+}
+
+impl Config {
+    /// The directory containing the settings file.
+    pub fn dir() -> Result<PathBuf, Error> {
+        Ok(dirs::config_dir().ok_or(OtherError::MissingConfigDir)?.join("lore-seeker-desktop"))
+    }
+
+    /// The path of the settings file.
+    pub fn path() -> Result<PathBuf, Error> {
+        Ok(Config::dir()?.join("config.json"))
+    }
+
+    /// Loads the settings, migrating them to the current format if necessary. If there is no settings file, the default settings are returned.
+    pub fn load() -> Result<Config, Error> {
+        let dir = Config::dir()?;
+        match fs::read(dir.join("config.json")) {
+            Ok(buf) => {
+                let (mut config, migrated) = Config::migrate(serde_json::from_slice(&buf)?)?;
+                config.update_interval_secs = config.update_interval_secs.max(MIN_UPDATE_INTERVAL_SECS); // a hand-edited interval of 0 would check for updates continuously
+                if migrated { config.save()?; }
+                Ok(config)
+            }
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => {
+                let (config, migrated) = Config::from_legacy_files()?;
+                if migrated {
+                    config.save()?;
+                    for name in &[LEGACY_SKIPPED_VERSIONS, LEGACY_UPDATE_CHANNEL] {
+                        match fs::remove_file(dir.join(name)) {
+                            Ok(()) => {}
+                            Err(ref e) if e.kind() == io::ErrorKind::NotFound => {}
+                            Err(e) => { return Err(e.into()); }
+                        }
+                    }
+                }
+                Ok(config)
+            }
+            Err(e) => Err(e.into())
+        }
+    }
+
+    /// Upgrades the contents of a settings file to the current format. Also returns whether any changes were made.
+    fn migrate(mut json: Value) -> Result<(Config, bool), Error> {
+        let version = json.get("version").and_then(Value::as_u64).unwrap_or(CURRENT_VERSION);
+        if version > CURRENT_VERSION { return Err(OtherError::UnsupportedVersion(version).into()); }
+        if let Value::Object(ref mut map) = json {
+            if version < 2 {
+                // version 2 replaced `base_url` with `server`, which also has an auth token
+                if let Some(base_url) = map.remove("base_url") {
+                    map.insert(format!("server"), json!({ "base_url": base_url }));
+                }
+            }
+            map.insert(format!("version"), json!(CURRENT_VERSION));
+        }
+        Ok((serde_json::from_value(json)?, version < CURRENT_VERSION))
+    }
+
+    /// Reads the plain text files used before the settings file was introduced. Also returns whether any were found.
+    fn from_legacy_files() -> Result<(Config, bool), Error> {
+        let dir = Config::dir()?;
+        let mut config = Config::default();
+        let mut found = false;
+        if let Some(text) = read_optional(dir.join(LEGACY_SKIPPED_VERSIONS))? {
+            config.skipped_versions = text.lines().filter_map(|line| Version::parse(line.trim()).ok()).collect();
+            found = true;
+        }
+        if let Some(text) = read_optional(dir.join(LEGACY_UPDATE_CHANNEL))? {
+            config.update_channel = UpdateChannel::from_name(text.trim()).unwrap_or_default();
+            found = true;
+        }
+        Ok((config, found))
+    }
+
+    /// Writes the settings file, replacing it atomically.
+    pub fn save(&self) -> Result<(), Error> {
+        let dir = Config::dir()?;
+        fs::create_dir_all(&dir)?;
+        util::write_atomic(dir.join("config.json"), serde_json::to_vec_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Returns `true` if the user chose not to be offered the update with the given version, built from the given commit, on the current update channel.
+    pub fn is_skipped(&self, version: &Version, commit: &str) -> bool {
+        match self.update_channel {
+            UpdateChannel::Nightly => self.skipped_commits.iter().any(|skipped| skipped == commit),
+            UpdateChannel::Stable | UpdateChannel::Beta => self.skipped_versions.contains(version)
+        }
+    }
+
+    /// Records that the user doesn't want to be offered the given update, by commit on the nightly channel and by version otherwise. Returns `false` if it was already skipped.
+    pub fn skip(&mut self, version: Version, commit: String) -> bool {
+        if self.is_skipped(&version, &commit) { return false; }
+        match self.update_channel {
+            UpdateChannel::Nightly => { self.skipped_commits.push(commit); }
+            UpdateChannel::Stable | UpdateChannel::Beta => { self.skipped_versions.push(version); }
+        }
+        true
+    }
+
+    /// Returns the Cockatrice paths to sync to: the configured data directory if there is one, otherwise the one Cockatrice uses.
+    pub fn cockatrice_paths(&self) -> Option<Paths> {
+        match self.cockatrice_data_dir {
+            Some(ref data_dir) => Some(Paths::new(data_dir)),
+            None => Paths::find()
+        }
+    }
+}
+
+/// Reads a text file, returning `None` if it doesn't exist.
+fn read_optional(path: PathBuf) -> io::Result<Option<String>> {
+    match fs::read_to_string(path) {
+        Ok(text) => Ok(Some(text)),
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn skip_nightly_by_commit() {
+        let mut config = Config { update_channel: UpdateChannel::Nightly, ..Config::default() };
+        let version = Version::new(0, 2, 4);
+        assert!(config.skip(version.clone(), "abc1234".to_owned()));
+        assert!(!config.skip(version.clone(), "abc1234".to_owned()));
+        assert!(config.is_skipped(&version, "abc1234"));
+        assert!(!config.is_skipped(&version, "def5678"));
+        assert!(config.skipped_versions.is_empty());
+        config.update_channel = UpdateChannel::Stable;
+        assert!(!config.is_skipped(&version, "abc1234"));
+        assert!(config.skip(version.clone(), "abc1234".to_owned()));
+        assert!(config.is_skipped(&version, "def5678"));
+    }
+}
@@ -0,0 +1,55 @@
+//! Binary patches between releases, so the self-updater doesn't have to download the entire executable for every release.
+
+use std::io;
+use flate2::{
+    Compression,
+    read::GzDecoder,
+    write::GzEncoder
+};
+use semver::Version;
+
+/// The number of previous releases the release script publishes patches from.
+pub const PREVIOUS_VERSIONS: usize = 3;
+
+/// The name of the release asset containing a patch which turns the given asset of the given release into the same asset of the release it's attached to.
+pub fn patch_name(asset: &str, from: &Version) -> String {
+    format!("{}.from-{}.patch", asset, from)
+}
+
+/// Creates a gzip-compressed bsdiff patch from `old` to `new`.
+pub fn diff(old: &[u8], new: &[u8]) -> io::Result<Vec<u8>> {
+    let mut encoder = GzEncoder::new(Vec::default(), Compression::best());
+    bsdiff::diff(old, new, &mut encoder)?;
+    encoder.finish()
+}
+
+/// Applies a patch created by `diff` to `old`.
+///
+/// Applying a patch to a file other than the one it was created from produces garbage rather than an error, so the result must be verified.
+pub fn apply(old: &[u8], patch: &[u8]) -> io::Result<Vec<u8>> {
+    let mut new = Vec::default();
+    bsdiff::patch(old, &mut GzDecoder::new(patch), &mut new)?;
+    Ok(new)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let old = b"Lore Seeker Desktop 0.2.4\0".repeat(100);
+        let mut new = old.clone();
+        new[20..25].copy_from_slice(b"0.3.0");
+        new.extend_from_slice(b"new feature");
+        for &(old, new) in &[(&old[..], &new[..]), (&new[..], &old[..]), (&[][..], &new[..]), (&old[..], &[][..]), (&old[..], &old[..])] {
+            let patch = diff(old, new).expect("failed to create patch");
+            assert_eq!(apply(old, &patch).expect("failed to apply patch"), new);
+        }
+    }
+
+    #[test]
+    fn malformed_patch() {
+        assert!(apply(b"old", b"not a patch").is_err());
+    }
+}
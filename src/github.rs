@@ -2,15 +2,22 @@
 
 #![allow(missing_docs)] //TODO
 
-use serde_derive::Deserialize;
+use serde_derive::{
+    Deserialize,
+    Serialize
+};
 use serde_json::json;
 
+static API_BASE: &'static str = "https://api.github.com";
+
 #[derive(Deserialize)]
 pub struct Release {
     pub assets: Vec<ReleaseAsset>,
     pub body: String,
+    pub draft: bool,
     pub id: u64,
     pub name: String,
+    pub prerelease: bool,
     pub tag_name: String,
     pub upload_url: String //TODO reqwest::Url
 }
@@ -33,39 +40,77 @@ pub struct Commit {
 }
 
 /// A GitHub repository. Provides API methods.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Repo {
     /// The GitHub user or organization who owns this repo.
     pub user: String,
     /// The name of the repo.
-    pub name: String
+    pub name: String,
+    /// The base URL of the GitHub API. Only overridden in tests.
+    #[serde(skip, default = "default_api_base")]
+    api_base: String
+}
+
+fn default_api_base() -> String {
+    API_BASE.to_owned()
 }
 
 impl Repo {
     pub fn new(user: impl ToString, name: impl ToString) -> Self {
         Repo {
             user: user.to_string(),
-            name: name.to_string()
+            name: name.to_string(),
+            api_base: default_api_base()
+        }
+    }
+
+    /// Returns a copy of this repo which sends API requests to the given base URL instead of GitHub.
+    #[cfg(test)]
+    pub(crate) fn with_api_base(&self, api_base: impl ToString) -> Self {
+        Repo {
+            api_base: api_base.to_string(),
+            ..self.clone()
         }
     }
 
     pub fn latest_release(&self, client: &reqwest::Client) -> Result<Release, reqwest::Error> {
         Ok(
-            client.get(&format!("https://api.github.com/repos/{}/{}/releases/latest", self.user, self.name))
+            client.get(&format!("{}/repos/{}/{}/releases/latest", self.api_base, self.user, self.name))
                 .send()?
                 .error_for_status()?
                 .json::<Release>()?
         )
     }
 
-    /// Creates a draft release, which can be published using `Repo::publish_release`.
-    pub fn create_release(&self, client: &reqwest::Client, name: String, tag_name: String, body: String) -> Result<Release, reqwest::Error> {
+    /// Returns the most recent releases, newest first. Includes drafts if the client is authorized to see them.
+    pub fn releases(&self, client: &reqwest::Client) -> Result<Vec<Release>, reqwest::Error> {
+        Ok(
+            client.get(&format!("{}/repos/{}/{}/releases", self.api_base, self.user, self.name))
+                .query(&[("per_page", "100")])
+                .send()?
+                .error_for_status()?
+                .json::<Vec<Release>>()?
+        )
+    }
+
+    /// Returns the release with the given tag name, or `None` if there is no such release.
+    pub fn release_by_tag(&self, client: &reqwest::Client, tag_name: &str) -> Result<Option<Release>, reqwest::Error> {
+        let response = client.get(&format!("{}/repos/{}/{}/releases/tags/{}", self.api_base, self.user, self.name, tag_name)).send()?;
+        if response.status() == reqwest::StatusCode::NOT_FOUND { return Ok(None); }
+        Ok(Some(response.error_for_status()?.json::<Release>()?))
+    }
+
+    /// Creates a draft release, which can be published using `Repo::publish_release`. If the tag doesn't exist yet, it's created at `target_commitish` when the release is published.
+    pub fn create_release(&self, client: &reqwest::Client, name: String, tag_name: String, target_commitish: &str, prerelease: bool, body: String) -> Result<Release, reqwest::Error> {
         Ok(
-            client.post(&format!("https://api.github.com/repos/{}/{}/releases", self.user, self.name))
+            client.post(&format!("{}/repos/{}/{}/releases", self.api_base, self.user, self.name))
                 .json(&json!({
                     "body": body,
                     "draft": true,
                     "name": name,
-                    "tag_name": tag_name
+                    "prerelease": prerelease,
+                    "tag_name": tag_name,
+                    "target_commitish": target_commitish
                 }))
                 .send()?
                 .error_for_status()?
@@ -73,9 +118,25 @@ impl Repo {
         )
     }
 
+    /// Deletes the given release. Its tag is kept, see `Repo::delete_tag`.
+    pub fn delete_release(&self, client: &reqwest::Client, release: Release) -> Result<(), reqwest::Error> {
+        client.delete(&format!("{}/repos/{}/{}/releases/{}", self.api_base, self.user, self.name, release.id))
+            .send()?
+            .error_for_status()?;
+        Ok(())
+    }
+
+    /// Deletes the tag with the given name. Returns `false` if there was no such tag.
+    pub fn delete_tag(&self, client: &reqwest::Client, tag_name: &str) -> Result<bool, reqwest::Error> {
+        let response = client.delete(&format!("{}/repos/{}/{}/git/refs/tags/{}", self.api_base, self.user, self.name, tag_name)).send()?;
+        if response.status() == reqwest::StatusCode::NOT_FOUND || response.status() == reqwest::StatusCode::UNPROCESSABLE_ENTITY { return Ok(false); }
+        response.error_for_status()?;
+        Ok(true)
+    }
+
     pub fn publish_release(&self, client: &reqwest::Client, release: Release) -> Result<Release, reqwest::Error> {
         Ok(
-            client.patch(&format!("https://api.github.com/repos/{}/{}/releases/{}", self.user, self.name, release.id))
+            client.patch(&format!("{}/repos/{}/{}/releases/{}", self.api_base, self.user, self.name, release.id))
                 .json(&json!({"draft": false}))
                 .send()?
                 .error_for_status()?
@@ -99,7 +160,7 @@ impl Repo {
 
     pub fn tags(&self, client: &reqwest::Client) -> Result<Vec<Tag>, reqwest::Error> {
         Ok(
-            client.get(&format!("https://api.github.com/repos/{}/{}/tags", self.user, self.name))
+            client.get(&format!("{}/repos/{}/{}/tags", self.api_base, self.user, self.name))
                 .send()?
                 .error_for_status()?
                 .json::<Vec<Tag>>()?
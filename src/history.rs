@@ -0,0 +1,111 @@
+//! The user's recent and saved searches.
+
+use std::{
+    collections::BTreeMap,
+    fmt,
+    fs,
+    io,
+    path::PathBuf
+};
+use serde_derive::{
+    Deserialize,
+    Serialize
+};
+use wrapped_enum::wrapped_enum;
+use crate::{
+    config::{
+        self,
+        Config
+    },
+    query,
+    util
+};
+
+wrapped_enum! {
+    /// An error that can occur while loading or saving the history.
+    #[derive(Debug)]
+    pub enum Error {
+        /// The config directory, where the history is stored, could not be determined.
+        Config(config::Error),
+        #[allow(missing_docs)]
+        Io(io::Error),
+        #[allow(missing_docs)]
+        Json(serde_json::Error)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::Config(ref e) => e.fmt(f),
+            Error::Io(ref e) => write!(f, "Could not access the search history file: {}", e),
+            Error::Json(ref e) => write!(f, "The search history file is invalid: {}", e)
+        }
+    }
+}
+
+/// The maximum number of recent searches that are remembered.
+pub const MAX_RECENT: usize = 50;
+
+/// Recent and saved searches, stored next to the settings file.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct History {
+    /// Recent search queries, most recent first, without duplicates.
+    pub recent: Vec<String>,
+    /// Saved search queries, keyed by name.
+    pub saved: BTreeMap<String, String>
+}
+
+impl History {
+    /// The path of the history file.
+    pub fn path() -> Result<PathBuf, Error> {
+        Ok(Config::dir()?.join("history.json"))
+    }
+
+    /// Loads the history. If there is no history file, an empty history is returned.
+    pub fn load() -> Result<History, Error> {
+        match fs::read(History::path()?) {
+            Ok(buf) => Ok(serde_json::from_slice(&buf)?),
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(History::default()),
+            Err(e) => Err(e.into())
+        }
+    }
+
+    /// Writes the history file, replacing it atomically.
+    pub fn save(&self) -> Result<(), Error> {
+        let dir = Config::dir()?;
+        fs::create_dir_all(&dir)?;
+        util::write_atomic(History::path()?, serde_json::to_vec_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Adds a query to the top of the recent searches, removing older occurrences and the oldest searches beyond `MAX_RECENT`.
+    ///
+    /// Queries are stored in canonical form if they can be parsed, so that e.g. `T:Elf` and `t:elf` count as the same search. Empty queries are not recorded.
+    pub fn record(&mut self, query: &str) {
+        let query = canonical(query);
+        if query.is_empty() { return; }
+        self.recent.retain(|recent| *recent != query);
+        self.recent.insert(0, query);
+        self.recent.truncate(MAX_RECENT);
+    }
+
+    /// Saves a query under the given name, replacing any saved search with the same name.
+    pub fn save_search(&mut self, name: impl ToString, query: &str) {
+        self.saved.insert(name.to_string(), canonical(query));
+    }
+
+    /// Removes the saved search with the given name. Returns `false` if there was none.
+    pub fn forget(&mut self, name: &str) -> bool {
+        self.saved.remove(name).is_some()
+    }
+}
+
+/// Returns the canonical form of the query, or the query with surrounding whitespace removed if it can't be parsed.
+fn canonical(text: &str) -> String {
+    match query::parse(text) {
+        Ok(query) => query.to_string(),
+        Err(_) => text.trim().to_owned()
+    }
+}
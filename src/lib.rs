@@ -5,7 +5,17 @@
 #![deny(missing_docs, unused, unused_qualifications)]
 #![forbid(unused_import_braces)]
 
+pub mod checksum;
+pub mod config;
+pub mod delta;
 pub mod github;
+pub mod history;
+pub mod lore_seeker;
+pub mod markdown;
+pub mod query;
+pub mod search;
+pub mod signing;
+#[cfg(test)] mod test_server;
 pub mod trice;
 pub mod update;
 pub mod util;
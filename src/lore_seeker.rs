@@ -0,0 +1,184 @@
+//! Access to a Lore Seeker server, either lore-seeker.cards or a self-hosted instance.
+
+use serde_derive::{
+    Deserialize,
+    Serialize
+};
+
+/// The address of the public Lore Seeker instance.
+pub static DEFAULT_BASE_URL: &'static str = "https://lore-seeker.cards";
+
+/// A Lore Seeker server.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Server {
+    /// The address of the server, e.g. `https://lore-seeker.cards`. A trailing slash is ignored.
+    pub base_url: String,
+    /// A token sent as `Authorization: Bearer` with API requests, for private instances.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auth_token: Option<String>
+}
+
+/// The number of results per page of the offline `search::Index`, and of `Server::search` if the server doesn't report its page size.
+pub const PAGE_SIZE: usize = 25;
+
+fn default_page_size() -> usize { PAGE_SIZE }
+
+/// A card in the search results.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct Card {
+    /// The card's name.
+    pub name: String,
+    /// The set code of the printing.
+    pub set: String,
+    /// The collector number of the printing within its set.
+    #[serde(default)]
+    pub number: Option<String>,
+    /// The mana cost in `{W}{U}` notation, if the card has one.
+    #[serde(default)]
+    pub mana_cost: Option<String>,
+    /// The full type line, e.g. `Legendary Creature — Elf Warrior`.
+    pub type_line: String,
+    /// The rules text.
+    #[serde(default)]
+    pub oracle_text: String,
+    /// The URL of the card image, if the server has one.
+    #[serde(default)]
+    pub image_url: Option<String>
+}
+
+/// A page of search results.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct SearchPage {
+    /// The cards on this page.
+    pub cards: Vec<Card>,
+    /// The number of this page, starting at 1.
+    pub page: usize,
+    /// The total number of cards matching the query.
+    pub total_cards: usize,
+    /// The maximum number of cards per page, as reported by the server.
+    #[serde(default = "default_page_size")]
+    pub page_size: usize
+}
+
+impl SearchPage {
+    /// The number of pages of results. There is always at least one page, even if there are no results.
+    pub fn total_pages(&self) -> usize {
+        let page_size = self.page_size.max(1); // guard against a server reporting a page size of 0
+        ((self.total_cards + page_size - 1) / page_size).max(1)
+    }
+
+    /// Whether there is a page after this one.
+    pub fn has_next(&self) -> bool {
+        self.page < self.total_pages()
+    }
+}
+
+impl Default for Server {
+    fn default() -> Server {
+        Server {
+            base_url: DEFAULT_BASE_URL.to_owned(),
+            auth_token: None
+        }
+    }
+}
+
+impl Server {
+    /// Returns the URL of the given path on this server. The path should start with a slash.
+    pub fn url(&self, path: &str) -> String {
+        format!("{}{}", self.base_url.trim_end_matches('/'), path)
+    }
+
+    /// Starts a GET request to the given path on this server, authenticated with the auth token if there is one.
+    pub fn get(&self, client: &reqwest::Client, path: &str) -> reqwest::RequestBuilder {
+        let request = client.get(&self.url(path));
+        match self.auth_token {
+            Some(ref token) => request.header(reqwest::header::AUTHORIZATION, format!("Bearer {}", token)),
+            None => request
+        }
+    }
+
+    /// Runs a search query and returns the given page of results, starting at 1.
+    ///
+    /// An empty query returns all cards, as on the website.
+    pub fn search(&self, client: &reqwest::Client, query: &str, page: usize) -> Result<SearchPage, reqwest::Error> {
+        Ok(
+            self.get(client, "/api/search")
+                .query(&[("q", if query.is_empty() { "*" } else { query }), ("page", &page.to_string())])
+                .send()?
+                .error_for_status()?
+                .json::<SearchPage>()?
+        )
+    }
+
+    /// Returns the URL of the search results page for the given query on the website.
+    pub fn search_url(&self, query: &str) -> String {
+        self.url(&format!("/card?q={}", urlencoding::encode(if query.is_empty() { "*" } else { query })))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        test_server::{
+            response,
+            serve
+        },
+        util
+    };
+    use super::*;
+
+    static SEARCH_RESPONSE: &'static str = r#"{"cards":[{"name":"Grove Warden","set":"VLN","number":"1","mana_cost":"{1}{G}","type_line":"Creature — Elf Warrior","oracle_text":"Vigilance"}],"page":2,"total_cards":26}"#;
+
+    #[test]
+    fn search_sends_auth_token() {
+        let (base_url, server) = serve(vec![response("200 OK", &["Content-Type: application/json"], SEARCH_RESPONSE.len(), SEARCH_RESPONSE.as_bytes())]);
+        let server_config = Server { base_url: format!("{}/", base_url), auth_token: Some("secret".to_owned()) };
+        let page = server_config.search(&util::client().expect("failed to create client"), "t:elf", 2).expect("search failed");
+        assert_eq!(page.cards[0].name, "Grove Warden");
+        let requests = server.join().expect("test server panicked");
+        assert!(requests[0].path().starts_with("/api/search?"));
+        assert!(requests[0].path().contains("page=2"));
+        assert_eq!(requests[0].header("authorization"), Some("Bearer secret"));
+    }
+
+    #[test]
+    fn search_without_auth_token() {
+        let (base_url, server) = serve(vec![response("200 OK", &["Content-Type: application/json"], SEARCH_RESPONSE.len(), SEARCH_RESPONSE.as_bytes())]);
+        let server_config = Server { base_url, auth_token: None };
+        server_config.search(&util::client().expect("failed to create client"), "", 1).expect("search failed");
+        let requests = server.join().expect("test server panicked");
+        assert!(requests[0].path().starts_with("/api/search?"));
+        assert_eq!(requests[0].header("authorization"), None);
+    }
+
+    #[test]
+    fn deserialize_search_page() {
+        let page = serde_json::from_str::<SearchPage>(r#"{
+            "cards": [
+                {"name": "Grove Warden", "set": "VLN", "number": "1", "mana_cost": "{1}{G}", "type_line": "Creature — Elf Warrior", "oracle_text": "Vigilance", "image_url": "https://lore-seeker.cards/cards/vln/1.png"},
+                {"name": "Shrine of the Lost", "set": "VLN", "type_line": "Land"}
+            ],
+            "page": 1,
+            "total_cards": 45,
+            "page_size": 20
+        }"#).expect("failed to parse search page");
+        assert_eq!(page.cards.len(), 2);
+        assert_eq!(page.cards[0].mana_cost.as_ref().map(String::as_str), Some("{1}{G}"));
+        assert_eq!(page.cards[1].number, None);
+        assert_eq!(page.cards[1].oracle_text, "");
+        assert_eq!(page.total_pages(), 3);
+        assert!(page.has_next());
+        let page = serde_json::from_str::<SearchPage>(SEARCH_RESPONSE).expect("failed to parse search page");
+        assert_eq!(page.page_size, PAGE_SIZE);
+        assert_eq!(page.total_pages(), 2);
+        assert!(!page.has_next());
+    }
+
+    #[test]
+    fn trailing_slash() {
+        let with_slash = Server { base_url: "https://lore-seeker.example/".to_owned(), auth_token: None };
+        let without_slash = Server { base_url: "https://lore-seeker.example".to_owned(), auth_token: None };
+        assert_eq!(with_slash.url("/api/search"), "https://lore-seeker.example/api/search");
+        assert_eq!(with_slash.url("/api/search"), without_slash.url("/api/search"));
+    }
+}
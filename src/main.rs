@@ -5,14 +5,22 @@
 #![windows_subsystem = "windows"]
 
 use std::{
+    path::PathBuf,
     process::exit,
+    sync::{
+        Arc,
+        Mutex,
+        mpsc::{
+            self,
+            Receiver,
+            TryRecvError
+        }
+    },
+    thread,
     time::Duration
 };
 use azul::{
-    dialogs::{
-        msg_box,
-        save_file_dialog
-    },
+    dialogs::msg_box,
     prelude::*,
     widgets::{
         button::Button,
@@ -24,84 +32,632 @@ use azul::{
     }
 };
 use open::that as open;
+use semver::Version;
 use lore_seeker_desktop::{
-    trice,
+    config::Config,
+    history::History,
+    lore_seeker::{
+        SearchPage,
+        Server
+    },
+    markdown::{
+        self,
+        Block
+    },
+    query::{
+        self,
+        ParseError
+    },
+    search::Index,
+    trice::{
+        self,
+        VersionStatus,
+        probe,
+        sync::{
+            self,
+            SyncReport
+        }
+    },
     update::{
-        download_update,
+        self,
+        UpdateChannel,
+        UpdateStatus,
+        self_update,
         update_check
     },
     util::*,
-    version::GIT_COMMIT_HASH
+    version::{
+        GIT_COMMIT_HASH,
+        RELEASE_PUBLIC_KEY
+    }
 };
 
 #[derive(Default)]
 struct Ls {
-    search_term: TextInputState
+    backup_removal: Option<Background<Result<(), String>>>,
+    config: Config,
+    history: History,
+    pending_update: Option<PendingUpdate>,
+    saved_search_name: TextInputState,
+    search: Option<Background<Result<SearchResults, String>>>,
+    search_results: Option<SearchResults>,
+    search_term: TextInputState,
+    settings: Option<SettingsForm>,
+    sync: Option<Background<Result<SyncReport, String>>>,
+    trice_check: Option<Background<Result<VersionStatus, String>>>,
+    trice_install: Option<Background<Result<(), trice::Error>>>,
+    /// The number of bytes of the Cockatrice download downloaded so far, and the total size if known.
+    trice_install_progress: Arc<Mutex<(u64, Option<u64>)>>,
+    /// The result of the last check, or the error message if it failed, e.g. because we're offline.
+    trice_status: Option<Result<VersionStatus, String>>,
+    update_download: Option<Background<Result<(), String>>>,
+    /// The number of bytes of the update downloaded so far, and the total size if known.
+    update_progress: Arc<Mutex<(u64, Option<u64>)>>
 }
 
-impl Layout for Ls {
-    fn layout(&self, info: LayoutInfo<Ls>) -> Dom<Ls> {
+/// The result of work done on a background thread, so that network requests don't freeze the window. Checked by `poll_timer`.
+struct Background<T>(Receiver<T>);
+
+impl<T: Send + 'static> Background<T> {
+    fn spawn(f: impl FnOnce() -> T + Send + 'static) -> Background<T> {
+        let (tx, rx) = mpsc::channel();
+        thread::spawn(move || { let _ = tx.send(f()); });
+        Background(rx)
+    }
+
+    /// Takes the result out of `slot` if the work is done, leaving `None` behind. A thread which panicked counts as done without a result.
+    fn poll(slot: &mut Option<Background<T>>) -> Option<T> {
+        let result = match *slot {
+            Some(Background(ref rx)) => rx.try_recv(),
+            None => { return None; }
+        };
+        match result {
+            Ok(value) => {
+                *slot = None;
+                Some(value)
+            }
+            Err(TryRecvError::Empty) => None,
+            Err(TryRecvError::Disconnected) => {
+                *slot = None;
+                None
+            }
+        }
+    }
+}
+
+/// The results of the last search, shown below the search bar.
+struct SearchResults {
+    /// Whether the results are from the local card database because the server couldn't be reached.
+    offline: bool,
+    query: String,
+    page: SearchPage
+}
+
+impl SearchResults {
+    fn dom(&self) -> Dom<Ls> {
+        let mut list = Dom::div();
+        for card in &self.page.cards {
+            let mut card_dom = Dom::div()
+                .with_child(Label::new(match card.mana_cost {
+                    Some(ref mana_cost) => format!("{} {}", card.name, mana_cost),
+                    None => card.name.clone()
+                }).dom())
+                .with_child(Label::new(card.type_line.clone()).dom());
+            if !card.oracle_text.is_empty() { card_dom.add_child(Label::new(card.oracle_text.clone()).dom()); }
+            card_dom.add_child(Label::new(match card.number {
+                Some(ref number) => format!("{} #{}", card.set.to_uppercase(), number),
+                None => card.set.to_uppercase()
+            }).dom());
+            list.add_child(card_dom);
+        }
+        let mut pagination = Dom::div();
+        if self.page.page > 1 {
+            pagination.add_child(Button::with_label("Previous").dom()
+                .with_callback(On::MouseUp, previous_page)
+            );
+        }
+        pagination.add_child(Label::new(format!(
+            "Page {} of {} ({} cards{})",
+            self.page.page,
+            self.page.total_pages(),
+            self.page.total_cards,
+            if self.offline { ", offline" } else { "" }
+        )).dom());
+        if self.page.has_next() {
+            pagination.add_child(Button::with_label("Next").dom()
+                .with_callback(On::MouseUp, next_page)
+            );
+        }
+        pagination.add_child(Button::with_label("Open in browser").dom()
+            .with_callback(On::MouseUp, open_search)
+        );
+        Dom::div()
+            .with_child(list)
+            .with_child(pagination)
+    }
+}
+
+/// The contents of the settings screen while it's open. Changes are only applied to the config when they're saved.
+struct SettingsForm {
+    auth_token: TextInputState,
+    base_url: TextInputState,
+    cockatrice_data_dir: TextInputState,
+    cockatrice_install_dir: TextInputState,
+    update_channel: UpdateChannel,
+    update_interval: TextInputState
+}
+
+impl SettingsForm {
+    fn new(config: &Config) -> SettingsForm {
+        let path_text = |path: &Option<PathBuf>| path.as_ref().map(|path| path.display().to_string()).unwrap_or_default();
+        SettingsForm {
+            auth_token: TextInputState::new(config.server.auth_token.clone().unwrap_or_default()),
+            base_url: TextInputState::new(config.server.base_url.clone()),
+            cockatrice_data_dir: TextInputState::new(path_text(&config.cockatrice_data_dir)),
+            cockatrice_install_dir: TextInputState::new(path_text(&config.cockatrice_install_dir)),
+            update_channel: config.update_channel,
+            update_interval: TextInputState::new((config.update_interval_secs / 60).to_string())
+        }
+    }
+
+    fn dom(&self, info: &LayoutInfo<Ls>, ls: &Ls) -> Dom<Ls> {
+        let field = |label: &str, state: &TextInputState| Dom::div()
+            .with_child(Label::new(label).dom())
+            .with_child(TextInput::new().bind(info.window, state, ls).dom(state));
         Dom::div()
-            .with_child(Dom::div() // search bar
-                .with_child(TextInput::new().bind(info.window, &self.search_term, self).dom(&self.search_term))
-                .with_child(Button::with_label("Search").dom()
-                    .with_callback(On::MouseUp, search)
+            .with_child(field("Lore Seeker address", &self.base_url))
+            .with_child(field("Access token (only needed for private servers)", &self.auth_token))
+            .with_child(field("Check for updates every (minutes, takes effect after restarting)", &self.update_interval))
+            .with_child(Button::with_label(format!("Update channel: {}", self.update_channel)).dom()
+                .with_callback(On::MouseUp, cycle_update_channel)
+            )
+            .with_child(field("Cockatrice data folder (leave empty to detect automatically)", &self.cockatrice_data_dir))
+            .with_child(field("Cockatrice install folder (leave empty for the default)", &self.cockatrice_install_dir))
+            .with_child(Dom::div()
+                .with_child(Button::with_label("Save").dom()
+                    .with_callback(On::MouseUp, save_settings)
+                )
+                .with_child(Button::with_label("Cancel").dom()
+                    .with_callback(On::MouseUp, close_settings)
                 )
             )
-            .with_child(Button::with_label("Install Cockatrice").dom()
-                .with_callback(On::MouseUp, install_trice)
+    }
+
+    /// Applies the form to a copy of the given config. Returns an error message if a field is invalid.
+    fn apply(&self, config: &Config) -> Result<Config, String> {
+        let path = |state: &TextInputState| if state.text.trim().is_empty() { None } else { Some(PathBuf::from(state.text.trim())) };
+        let base_url = self.base_url.text.trim().trim_end_matches('/');
+        if !base_url.starts_with("https://") && !base_url.starts_with("http://") { return Err(format!("The Lore Seeker address must start with https://")); }
+        let update_interval = match self.update_interval.text.trim().parse::<u64>() {
+            Ok(minutes) if minutes > 0 => minutes * 60,
+            _ => { return Err(format!("The update interval must be a positive whole number of minutes.")); }
+        };
+        let auth_token = self.auth_token.text.trim();
+        Ok(Config {
+            server: Server {
+                base_url: base_url.to_owned(),
+                auth_token: if auth_token.is_empty() { None } else { Some(auth_token.to_owned()) }
+            },
+            cockatrice_data_dir: path(&self.cockatrice_data_dir),
+            cockatrice_install_dir: path(&self.cockatrice_install_dir),
+            update_channel: self.update_channel,
+            update_interval_secs: update_interval,
+            ..config.clone()
+        })
+    }
+}
+
+/// An available update of Lore Seeker Desktop which the user hasn't responded to yet.
+struct PendingUpdate {
+    version: Version,
+    commit: String,
+    notes: Vec<Block>
+}
+
+impl PendingUpdate {
+    /// The update prompt, or the progress of the update if `progress` is given because it's being installed.
+    fn dom(&self, progress: Option<(u64, Option<u64>)>) -> Dom<Ls> {
+        let mut notes = Dom::div();
+        for block in &self.notes {
+            notes.add_child(match *block {
+                Block::Heading(ref text) | Block::Paragraph(ref text) => Label::new(text.clone()).dom(),
+                Block::ListItem(ref text) => Label::new(format!("• {}", text)).dom()
+            });
+        }
+        Dom::div()
+            .with_child(Label::new(format!("Lore Seeker Desktop {} is available.", self.version)).dom())
+            .with_child(notes)
+            .with_child(match progress {
+                Some((downloaded, Some(total))) if downloaded >= total => Label::new("Installing update…").dom(),
+                Some((downloaded, Some(total))) => Label::new(format!("Downloading update: {}%", downloaded * 100 / total)).dom(),
+                Some((downloaded, None)) => Label::new(format!("Downloading update: {} KB", downloaded / 1024)).dom(),
+                None => Dom::div()
+                    .with_child(Button::with_label("Update").dom()
+                        .with_callback(On::MouseUp, install_update)
+                    )
+                    .with_child(Button::with_label("Later").dom()
+                        .with_callback(On::MouseUp, postpone_update)
+                    )
+                    .with_child(Button::with_label("Skip this version").dom()
+                        .with_callback(On::MouseUp, skip_update)
+                    )
+            })
+    }
+}
+
+/// The number of recent searches shown below the search bar.
+const RECENT_SHOWN: usize = 10;
+
+impl Ls {
+    /// Recent and saved searches. The buttons for each are the only children of their list, so the index of the clicked button is the index of the search.
+    fn history_dom(&self, info: &LayoutInfo<Ls>) -> Dom<Ls> {
+        let mut dom = Dom::div();
+        if !self.history.recent.is_empty() {
+            let mut recent = Dom::div();
+            for query in self.history.recent.iter().take(RECENT_SHOWN) {
+                recent.add_child(Button::with_label(query.clone()).dom()
+                    .with_callback(On::MouseUp, run_recent_search)
+                );
+            }
+            dom.add_child(Dom::div()
+                .with_child(Label::new("Recent searches").dom())
+                .with_child(recent)
+                .with_child(Button::with_label("Clear").dom()
+                    .with_callback(On::MouseUp, clear_history)
+                )
+            );
+        }
+        let mut saved = Dom::div();
+        for name in self.history.saved.keys() {
+            saved.add_child(Button::with_label(name.clone()).dom()
+                .with_callback(On::MouseUp, run_saved_search)
+            );
+        }
+        dom.with_child(Dom::div()
+            .with_child(Label::new("Saved searches").dom())
+            .with_child(saved)
+            .with_child(TextInput::new().bind(info.window, &self.saved_search_name, self).dom(&self.saved_search_name))
+            .with_child(Button::with_label("Save current search").dom()
+                .with_callback(On::MouseUp, save_search)
             )
-            .with_child(Label::new(format!("Lore Seeker Desktop version {}", &GIT_COMMIT_HASH[..7])).dom())
+        )
+    }
+
+    fn save_history(&self) {
+        if let Err(e) = self.history.save() {
+            error_message("Lore Seeker: Error saving search history", &format!("{}", e));
+        }
+    }
+
+    /// The install or update button for Cockatrice, or its status if there's nothing to do or it's being installed.
+    fn trice_dom(&self) -> Dom<Ls> {
+        if self.trice_install.is_some() {
+            return Label::new(match *self.trice_install_progress.lock().expect("Cockatrice install progress lock poisoned") {
+                (downloaded, Some(total)) if downloaded >= total => "Installing Cockatrice…".to_owned(),
+                (downloaded, Some(total)) => format!("Downloading Cockatrice: {}%", downloaded * 100 / total),
+                (downloaded, None) => format!("Downloading Cockatrice: {} KB", downloaded / 1024)
+            }).dom();
+        }
+        match self.trice_status {
+            Some(Ok(VersionStatus::UpToDate(ref installed))) => Label::new(format!("Cockatrice {} is up to date", installed)).dom(),
+            Some(Ok(VersionStatus::UpdateAvailable { ref latest, .. })) => Button::with_label(format!("Update Cockatrice to {}", latest)).dom()
+                .with_callback(On::MouseUp, install_trice),
+            Some(Ok(VersionStatus::NotInstalled)) => Button::with_label("Install Cockatrice").dom()
+                .with_callback(On::MouseUp, install_trice),
+            Some(Err(ref msg)) => Dom::div()
+                .with_child(Label::new(format!("Unable to check for Cockatrice: {}", msg)).dom())
+                .with_child(Button::with_label("Retry").dom()
+                    .with_callback(On::MouseUp, check_trice)
+                ),
+            None => Label::new("Checking for Cockatrice…").dom()
+        }
+    }
+
+    /// Starts syncing Lore Seeker sets into Cockatrice on a background thread, unless a sync is already running or Cockatrice is not set up yet.
+    fn start_sync(&mut self) {
+        if self.sync.is_some() { return; }
+        let paths = match self.config.cockatrice_paths() {
+            Some(paths) => paths,
+            None => { return; } // nothing to sync to, try again on the next timer tick
+        };
+        let server = self.config.server.clone();
+        self.sync = Some(Background::spawn(move || {
+            let client = client().map_err(|e| format!("Error creating client: {}", e))?;
+            sync::sync(&client, &server, &paths).map_err(|e| format!("{}", e))
+        }));
+    }
+
+    /// Starts checking whether Cockatrice is installed and up to date on a background thread, since this requires a network request and running Cockatrice.
+    fn start_trice_check(&mut self) {
+        let config = self.config.clone();
+        self.trice_check = Some(Background::spawn(move || trice_status(&config)));
+    }
+
+    /// Starts downloading and installing Cockatrice on a background thread. Its progress is shown in place of the install button.
+    fn start_trice_install(&mut self, allow_unverified: bool) {
+        let options = trice::InstallOptions {
+            allow_unverified,
+            repo: self.config.cockatrice_repo.clone(),
+            target_dir: self.config.cockatrice_install_dir.clone(),
+            ..trice::InstallOptions::default()
+        };
+        let progress = Arc::new(Mutex::new((0, None)));
+        self.trice_install_progress = Arc::clone(&progress);
+        self.trice_install = Some(Background::spawn(move || trice::install(&options, |downloaded, total| {
+            *progress.lock().expect("Cockatrice install progress lock poisoned") = (downloaded, total);
+        })));
     }
 }
 
-fn install_trice(_: CallbackInfo<Ls>) -> UpdateScreen {
-    if let Err(e) = trice::install(false) {
-        error_message("Lore Seeker: Error installing Cockatrice", &format!("{}", e));
+impl Layout for Ls {
+    fn layout(&self, info: LayoutInfo<Ls>) -> Dom<Ls> {
+        if let Some(ref settings) = self.settings { return settings.dom(&info, self); }
+        let mut dom = Dom::div();
+        if let Some(ref update) = self.pending_update {
+            let progress = if self.update_download.is_some() { Some(*self.update_progress.lock().expect("update progress lock poisoned")) } else { None };
+            dom.add_child(update.dom(progress));
+        }
+        dom.add_child(Dom::div() // search bar
+            .with_child(TextInput::new().bind(info.window, &self.search_term, self).dom(&self.search_term))
+            .with_child(Button::with_label("Search").dom()
+                .with_callback(On::MouseUp, search)
+            )
+        );
+        if let Err(e) = query::parse(&self.search_term.text) { dom.add_child(query_error_dom(&self.search_term.text, &e)); }
+        dom.add_child(self.history_dom(&info));
+        if self.search.is_some() {
+            dom.add_child(Label::new("Searching…").dom());
+        } else if let Some(ref results) = self.search_results {
+            dom.add_child(results.dom());
+        }
+        dom
+            .with_child(self.trice_dom())
+            .with_child(Label::new(format!("Lore Seeker Desktop version {}", &GIT_COMMIT_HASH[..7])).dom())
+            .with_child(Button::with_label("Settings").dom()
+                .with_callback(On::MouseUp, open_settings)
+            )
     }
-    DontRedraw
 }
 
+/// Starts installing Cockatrice on a background thread, unless it's already being installed.
+fn install_trice(info: CallbackInfo<Ls>) -> UpdateScreen {
+    let ls = &mut info.state.data;
+    if ls.trice_install.is_some() { return DontRedraw; }
+    ls.start_trice_install(false);
+    Redraw
+}
+
+fn check_trice(info: CallbackInfo<Ls>) -> UpdateScreen {
+    info.state.data.trice_status = None;
+    info.state.data.start_trice_check();
+    Redraw
+}
+
+/// Checks whether Cockatrice is installed and up to date. Returns an error message if this can't be determined, e.g. because we're offline.
+fn trice_status(config: &Config) -> Result<VersionStatus, String> {
+    let client = client().map_err(|e| format!("Error creating client: {}", e))?;
+    trice::check_version(&client, &config.cockatrice_repo, &*probe::default_probe(config.cockatrice_install_dir.as_ref().map(PathBuf::as_path))).map_err(|e| format!("{}", e))
+}
+
+/// Shows where the error in the search bar is, by putting the erroneous part of the query in brackets.
+fn query_error_dom(text: &str, e: &ParseError) -> Dom<Ls> {
+    let marked = if e.span.start == e.span.end {
+        format!("{} [here]{}", &text[..e.span.start], &text[e.span.end..])
+    } else {
+        format!("{}[{}]{}", &text[..e.span.start], &text[e.span.clone()], &text[e.span.end..])
+    };
+    Dom::div()
+        .with_child(Label::new(format!("Invalid query: {}", e)).dom())
+        .with_child(Label::new(marked).dom())
+}
+
+/// Runs the search in the search bar and shows the first page of results.
+///
+/// Queries with syntax errors are rejected, but unknown keywords and values are sent to the server, which supports more of them than the offline search.
 fn search(info: CallbackInfo<Ls>) -> UpdateScreen {
-    let query = &info.state.data.search_term.text;
-    if let Err(e) = open(&format!("https://lore-seeker.cards/card?q={}", urlencoding::encode(if query.is_empty() { "*" } else { &query }))) {
+    let query = info.state.data.search_term.text.clone();
+    if let Err(e) = query::parse(&query) {
+        if e.is_syntax_error() { return DontRedraw; } // already shown below the search bar
+    }
+    run_search(&mut info.state.data, query)
+}
+
+/// Runs the given query, puts it into the search bar, and records it in the search history.
+fn run_search(ls: &mut Ls, query: String) -> UpdateScreen {
+    ls.search_term = TextInputState::new(query.clone());
+    ls.history.record(&query);
+    ls.save_history();
+    show_page(ls, query, 1);
+    Redraw // the history changed even if the search failed
+}
+
+fn run_recent_search(info: CallbackInfo<Ls>) -> UpdateScreen {
+    let query = match info.target_index_in_parent().and_then(|idx| info.state.data.history.recent.get(idx)) {
+        Some(query) => query.clone(),
+        None => { return DontRedraw; }
+    };
+    run_search(&mut info.state.data, query)
+}
+
+fn run_saved_search(info: CallbackInfo<Ls>) -> UpdateScreen {
+    let query = match info.target_index_in_parent().and_then(|idx| info.state.data.history.saved.values().nth(idx)) {
+        Some(query) => query.clone(),
+        None => { return DontRedraw; }
+    };
+    run_search(&mut info.state.data, query)
+}
+
+fn clear_history(info: CallbackInfo<Ls>) -> UpdateScreen {
+    info.state.data.history.recent.clear();
+    info.state.data.save_history();
+    Redraw
+}
+
+/// Saves the query in the search bar under the name in the text field next to the button.
+fn save_search(info: CallbackInfo<Ls>) -> UpdateScreen {
+    let ls = &mut info.state.data;
+    let name = ls.saved_search_name.text.trim().to_owned();
+    if name.is_empty() {
+        error_message("Lore Seeker: Error saving search", "Please enter a name for the search.");
+        return DontRedraw;
+    }
+    ls.history.save_search(name, &ls.search_term.text);
+    ls.saved_search_name = TextInputState::default();
+    ls.save_history();
+    Redraw
+}
+
+fn previous_page(info: CallbackInfo<Ls>) -> UpdateScreen {
+    let (query, page) = match info.state.data.search_results {
+        Some(ref results) => (results.query.clone(), results.page.page - 1),
+        None => { return DontRedraw; }
+    };
+    show_page(&mut info.state.data, query, page)
+}
+
+fn next_page(info: CallbackInfo<Ls>) -> UpdateScreen {
+    let (query, page) = match info.state.data.search_results {
+        Some(ref results) => (results.query.clone(), results.page.page + 1),
+        None => { return DontRedraw; }
+    };
+    show_page(&mut info.state.data, query, page)
+}
+
+/// Starts a search on a background thread. The results replace the current ones once `poll_timer` picks them up. If a search is already running, its results are discarded.
+fn show_page(ls: &mut Ls, query: String, page: usize) -> UpdateScreen {
+    let config = ls.config.clone();
+    ls.search = Some(Background::spawn(move || search_page(&config, query, page)));
+    Redraw
+}
+
+/// Runs a search on the server, falling back to the card database synced into Cockatrice if the server can't be reached. Returns an error message if neither can be used.
+fn search_page(config: &Config, query: String, page: usize) -> Result<SearchResults, String> {
+    let online_error = match client() {
+        Ok(client) => match config.server.search(&client, &query, page) {
+            Ok(page) => { return Ok(SearchResults { offline: false, query, page }); }
+            Err(e) => format!("{}", e)
+        },
+        Err(e) => format!("Error creating client: {}", e)
+    };
+    let index = match config.cockatrice_paths().map(|paths| Index::open(&paths)) {
+        Some(Ok(index)) => index,
+        Some(Err(_)) | None => { return Err(online_error); }
+    };
+    match query::parse(&query) {
+        Ok(parsed) => Ok(SearchResults { offline: true, page: index.search_page(&parsed, page), query }),
+        Err(e) => Err(format!("{}\n\nThe offline search could not be used: {}", online_error, e))
+    }
+}
+
+/// Opens the current search on the website.
+fn open_search(info: CallbackInfo<Ls>) -> UpdateScreen {
+    let ls = &info.state.data;
+    let query = match ls.search_results {
+        Some(ref results) => &results.query,
+        None => &ls.search_term.text
+    };
+    if let Err(e) = open(&ls.config.server.search_url(query)) {
         error_message("Lore Seeker: Error opening website", &format!("{:?}", e));
     }
     DontRedraw
 }
 
-fn update_timer(_: TimerCallbackInfo<Ls>) -> (UpdateScreen, TerminateTimer) {
+/// Starts downloading and installing the pending update on a background thread. Its progress is shown in place of the update prompt.
+fn install_update(info: CallbackInfo<Ls>) -> UpdateScreen {
+    let ls = &mut info.state.data;
+    if ls.update_download.is_some() { return DontRedraw; }
+    let config = ls.config.clone();
+    let progress = Arc::new(Mutex::new((0, None)));
+    ls.update_progress = Arc::clone(&progress);
+    ls.update_download = Some(Background::spawn(move || {
+        let client = client().map_err(|e| format!("Error creating client: {}", e))?;
+        self_update(&client, &config, |downloaded, total| {
+            *progress.lock().expect("update progress lock poisoned") = (downloaded, total);
+        }).map_err(|e| format!("{}", e))
+    }));
+    Redraw
+}
+
+fn postpone_update(info: CallbackInfo<Ls>) -> UpdateScreen {
+    info.state.data.pending_update = None; // the update will be offered again on the next update check
+    Redraw
+}
+
+fn skip_update(info: CallbackInfo<Ls>) -> UpdateScreen {
+    let ls = &mut info.state.data;
+    if let Some(update) = ls.pending_update.take() {
+        if ls.config.skip(update.version, update.commit) {
+            if let Err(e) = ls.config.save() {
+                error_message("Lore Seeker: Error saving settings", &format!("{}", e));
+            }
+        }
+    }
+    Redraw
+}
+
+fn open_settings(info: CallbackInfo<Ls>) -> UpdateScreen {
+    info.state.data.settings = Some(SettingsForm::new(&info.state.data.config));
+    Redraw
+}
+
+fn close_settings(info: CallbackInfo<Ls>) -> UpdateScreen {
+    info.state.data.settings = None;
+    Redraw
+}
+
+fn save_settings(info: CallbackInfo<Ls>) -> UpdateScreen {
+    let ls = &mut info.state.data;
+    let config = match ls.settings {
+        Some(ref settings) => match settings.apply(&ls.config) {
+            Ok(config) => config,
+            Err(msg) => {
+                error_message("Lore Seeker: Invalid settings", &msg);
+                return DontRedraw;
+            }
+        },
+        None => { return DontRedraw; }
+    };
+    if let Err(e) = config.save() {
+        error_message("Lore Seeker: Error saving settings", &format!("{}", e));
+        return DontRedraw;
+    }
+    if config.update_channel != ls.config.update_channel { ls.pending_update = None; } // the pending update may not be on the new channel
+    let resync = config.server != ls.config.server || config.cockatrice_data_dir != ls.config.cockatrice_data_dir;
+    ls.config = config;
+    ls.settings = None;
+    if resync { ls.start_sync(); } // also registers the new server as a picture source
+    Redraw
+}
+
+/// Switches to the next less stable update channel, or back to stable after nightly.
+fn cycle_update_channel(info: CallbackInfo<Ls>) -> UpdateScreen {
+    if let Some(ref mut settings) = info.state.data.settings {
+        let idx = UpdateChannel::ALL.iter().position(|&channel| channel == settings.update_channel).unwrap_or_default();
+        settings.update_channel = UpdateChannel::ALL[(idx + 1) % UpdateChannel::ALL.len()];
+    }
+    Redraw
+}
+
+fn update_timer(info: TimerCallbackInfo<Ls>) -> (UpdateScreen, TerminateTimer) {
+    if info.state.update_download.is_some() { return (DontRedraw, TerminateTimer::Continue); } // already installing an update
     match client() {
         Ok(client) => {
-            match update_check(&client) {
-                Ok(true) => (DontRedraw, TerminateTimer::Continue),
-                Ok(false) => if yesno("An update for Lore Seeker Desktop is available. Download now?") {
-                    match save_file_dialog(None) {
-                        Some(save_path) => match download_update(&client, save_path) {
-                            Ok(()) => {
-                                msg_box("Update downloaded. This version of Lore Seeker Desktop will now close. Please open the new version.");
-                                exit(0); //TODO exit app cleanly or even auto-restart
-                            }
-                            Err(e) => {
-                                error_message("Lore Seeker: Error downloading update", &format!("{}", e));
-                                (DontRedraw, TerminateTimer::Continue)
-                            }
-                        },
-                        None => {
-                            error_message("Lore Seeker: Error checking for updates", &format!("Error determining save path"));
-                            (DontRedraw, TerminateTimer::Continue)
-                        }
-                    }
-                } else {
-                    msg_box("Lore Seeker update ignored, will stop checking for updates. Restart Lore Seeker to resume update checks.");
-                    (DontRedraw, TerminateTimer::Terminate)
-                },
+            match update_check(&client, &info.state.config) {
+                Ok(UpdateStatus::UpToDate) | Ok(UpdateStatus::AheadOfRelease) => (DontRedraw, TerminateTimer::Continue),
+                Ok(UpdateStatus::UpdateAvailable { version, commit, notes }) => {
+                    if info.state.config.is_skipped(&version, &commit) { return (DontRedraw, TerminateTimer::Continue); }
+                    info.state.pending_update = Some(PendingUpdate {
+                        notes: markdown::blocks(&notes),
+                        version,
+                        commit
+                    });
+                    (Redraw, TerminateTimer::Continue)
+                }
                 Err(e) => {
                     error_message("Lore Seeker: Error checking for updates", &format!("{}", e));
                     (DontRedraw, TerminateTimer::Continue)
                 }
             }
-            //TODO check for updated Cockatrice files
         }
         Err(e) => {
             error_message("Lore Seeker: Error checking for updates", &format!("Error creating client: {}", e));
@@ -110,9 +666,91 @@ fn update_timer(_: TimerCallbackInfo<Ls>) -> (UpdateScreen, TerminateTimer) {
     }
 }
 
+fn sync_timer(info: TimerCallbackInfo<Ls>) -> (UpdateScreen, TerminateTimer) {
+    info.state.start_sync();
+    (DontRedraw, TerminateTimer::Continue)
+}
+
+/// How often `poll_timer` checks whether background work has finished.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Reports the results of work done on background threads.
+fn poll_timer(info: TimerCallbackInfo<Ls>) -> (UpdateScreen, TerminateTimer) {
+    let mut redraw = DontRedraw;
+    if info.state.update_download.is_some() {
+        match Background::poll(&mut info.state.update_download) {
+            Some(Ok(())) => { exit(0); } // the new version is already running
+            Some(Err(msg)) => {
+                error_message("Lore Seeker: Error installing update", &msg);
+                info.state.pending_update = None;
+            }
+            None => {}
+        }
+        redraw = Redraw; // show the progress, or remove it when done
+    }
+    if let Some(Err(msg)) = Background::poll(&mut info.state.backup_removal) {
+        error_message("Lore Seeker: Error cleaning up after update", &msg);
+    }
+    if info.state.trice_install.is_some() {
+        match Background::poll(&mut info.state.trice_install) {
+            Some(Err(trice::Error::Other(trice::OtherError::MissingChecksum))) => if yesno("This Cockatrice release does not include a checksum, so Lore Seeker can't check whether the download is corrupted or has been tampered with. Install it anyway?") {
+                info.state.start_trice_install(true);
+            },
+            Some(result) => {
+                match result {
+                    Ok(()) => { msg_box("Cockatrice has been installed."); }
+                    Err(e) => { error_message("Lore Seeker: Error installing Cockatrice", &format!("{}", e)); }
+                }
+                info.state.trice_status = None;
+                info.state.start_trice_check();
+                info.state.start_sync(); // the data directory may have been created by the installer
+            }
+            None => {}
+        }
+        redraw = Redraw; // show the progress, or the new status when done
+    }
+    if info.state.search.is_some() {
+        match Background::poll(&mut info.state.search) {
+            Some(Ok(results)) => { info.state.search_results = Some(results); }
+            Some(Err(msg)) => { error_message("Lore Seeker: Error searching", &msg); }
+            None => {}
+        }
+        if info.state.search.is_none() { redraw = Redraw; } // replace "Searching…" with the results
+    }
+    if let Some(status) = Background::poll(&mut info.state.trice_check) {
+        info.state.trice_status = Some(status);
+        redraw = Redraw;
+    }
+    match Background::poll(&mut info.state.sync) {
+        Some(Ok(report)) => if !report.is_empty() {
+            msg_box(&format!("Lore Seeker sets have been updated in Cockatrice.\n\n{}", report));
+        },
+        Some(Err(msg)) => { error_message("Lore Seeker: Error updating Cockatrice files", &msg); }
+        None => {}
+    }
+    (redraw, TerminateTimer::Continue)
+}
+
 fn main() {
-    let mut app = App::new(Ls::default(), AppConfig::default()).unwrap();
+    let backup_removal = Background::spawn(|| update::remove_backup().map_err(|e| format!("{}", e)));
+    let config = Config::load().unwrap_or_else(|e| {
+        error_message("Lore Seeker: Error loading settings", &format!("{}", e));
+        Config::default()
+    });
+    let interval = Duration::from_secs(config.update_interval_secs);
+    let history = History::load().unwrap_or_else(|e| {
+        error_message("Lore Seeker: Error loading search history", &format!("{}", e));
+        History::default()
+    });
+    let mut ls = Ls { backup_removal: Some(backup_removal), config, history, ..Ls::default() };
+    ls.start_trice_check();
+    ls.start_sync();
+    let mut app = App::new(ls, AppConfig::default()).unwrap();
     let window = app.create_window(WindowCreateOptions::default(), css::native()).unwrap();
-    app.app_state.add_timer(TimerId::new(), Timer::new(update_timer).with_interval(Duration::from_secs(3600)));
+    if RELEASE_PUBLIC_KEY.is_some() && update::is_supported() { // builds without the release key, e.g. development builds, can't verify updates, and there are no builds to update to on some platforms, so update checks are disabled
+        app.app_state.add_timer(TimerId::new(), Timer::new(update_timer).with_interval(interval));
+    }
+    app.app_state.add_timer(TimerId::new(), Timer::new(sync_timer).with_interval(interval));
+    app.app_state.add_timer(TimerId::new(), Timer::new(poll_timer).with_interval(POLL_INTERVAL));
     app.run(window).unwrap();
 }
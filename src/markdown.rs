@@ -0,0 +1,188 @@
+//! A minimal Markdown-to-plain-text converter for displaying release notes.
+
+/// A block of text in a Markdown document, with inline formatting removed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Block {
+    /// A heading (`# ...`).
+    Heading(String),
+    /// An item of a bulleted or numbered list.
+    ListItem(String),
+    /// A paragraph. Consecutive lines are joined with spaces.
+    Paragraph(String)
+}
+
+/// Splits a Markdown document into blocks and removes inline formatting like emphasis, code spans, and link URLs.
+///
+/// This only supports the subset of Markdown commonly used in release notes. Anything else is passed through as text.
+pub fn blocks(markdown: &str) -> Vec<Block> {
+    let mut blocks = Vec::default();
+    let mut paragraph = Vec::<&str>::default();
+    fn end_paragraph(blocks: &mut Vec<Block>, paragraph: &mut Vec<&str>) {
+        if !paragraph.is_empty() {
+            blocks.push(Block::Paragraph(inline(&paragraph.join(" "))));
+            paragraph.clear();
+        }
+    }
+
+    for line in markdown.lines() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with("```") {
+            end_paragraph(&mut blocks, &mut paragraph);
+        } else if trimmed.starts_with('#') {
+            end_paragraph(&mut blocks, &mut paragraph);
+            blocks.push(Block::Heading(inline(trimmed.trim_start_matches('#').trim())));
+        } else if let Some(item) = list_item(trimmed) {
+            end_paragraph(&mut blocks, &mut paragraph);
+            blocks.push(Block::ListItem(inline(item)));
+        } else if paragraph.is_empty() && line.starts_with(char::is_whitespace) && blocks.last().map_or(false, |block| match *block { Block::ListItem(_) => true, _ => false }) {
+            // continuation of a list item
+            if let Some(Block::ListItem(ref mut text)) = blocks.last_mut() {
+                text.push(' ');
+                text.push_str(&inline(trimmed));
+            }
+        } else {
+            paragraph.push(trimmed);
+        }
+    }
+    end_paragraph(&mut blocks, &mut paragraph);
+    blocks
+}
+
+/// If the line is a list item (`* `, `- `, `+ `, or `1. `), returns its text.
+fn list_item(line: &str) -> Option<&str> {
+    if line.starts_with("* ") || line.starts_with("- ") || line.starts_with("+ ") { return Some(line[2..].trim_start()); }
+    let digits = line.find(|c: char| !c.is_ascii_digit())?;
+    if digits > 0 && line[digits..].starts_with(". ") { Some(line[digits + 2..].trim_start()) } else { None }
+}
+
+/// Removes inline formatting from a line of Markdown.
+fn inline(text: &str) -> String {
+    let mut result = String::default();
+    let mut rest = text;
+    while let Some(c) = rest.chars().next() {
+        match c {
+            '_' if result.chars().next_back().map_or(false, char::is_alphanumeric) && rest[1..].chars().next().map_or(false, char::is_alphanumeric) => {
+                // intraword underscore, e.g. in a file name
+                result.push(c);
+                rest = &rest[1..];
+            }
+            '*' | '_' | '`' => {
+                let run = rest.len() - rest.trim_start_matches(c).len();
+                if result.chars().next_back().map_or(true, char::is_whitespace) && rest[run..].chars().next().map_or(true, char::is_whitespace) {
+                    // surrounded by whitespace, so not formatting, e.g. `2 * 3`
+                    result.push_str(&rest[..run]);
+                }
+                rest = &rest[run..];
+            }
+            '\\' => {
+                let escaped = rest[1..].chars().next();
+                result.push(escaped.unwrap_or('\\')); // a backslash at the end of a line escapes nothing
+                rest = &rest[1 + escaped.map_or(0, char::len_utf8)..];
+            }
+            '!' if rest.starts_with("![") => { rest = &rest[1..]; } // render images as their alt text
+            '[' => {
+                // link: keep the text, drop the URL
+                let link = matching_close(rest, '[', ']').and_then(|close| if rest[close + 1..].starts_with('(') {
+                    Some((close, close + 1 + matching_close(&rest[close + 1..], '(', ')')?))
+                } else {
+                    None
+                });
+                match link {
+                    Some((close, end)) => {
+                        result.push_str(&inline(&rest[1..close]));
+                        rest = &rest[end + 1..];
+                    }
+                    _ => {
+                        result.push(c);
+                        rest = &rest[1..];
+                    }
+                }
+            }
+            c => {
+                result.push(c);
+                rest = &rest[c.len_utf8()..];
+            }
+        }
+    }
+    result
+}
+
+/// Returns the index of the `close` bracket matching the `open` bracket at the start of `text`, skipping over nested pairs.
+fn matching_close(text: &str, open: char, close: char) -> Option<usize> {
+    let mut depth = 0;
+    for (idx, c) in text.char_indices() {
+        if c == open {
+            depth += 1;
+        } else if c == close {
+            depth -= 1;
+            if depth == 0 { return Some(idx); }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn inline_formatting() {
+        for &(markdown, expected) in &[
+            // emphasis and code spans
+            ("*italic* and **bold**", "italic and bold"),
+            ("_italic_ and __bold__", "italic and bold"),
+            ("***both***", "both"),
+            ("run `lore-seeker-search`", "run lore-seeker-search"),
+            // intraword underscores
+            ("lore_seeker_desktop.exe", "lore_seeker_desktop.exe"),
+            ("_lore_seeker_", "lore_seeker"),
+            // escapes
+            (r"\*not italic\*", "*not italic*"),
+            (r"back\\slash", r"back\slash"),
+            (r"\[not a link\](url)", "[not a link](url)"),
+            (r"trailing\", r"trailing\"),
+            // links and images
+            ("[Cockatrice](https://cockatrice.github.io/)", "Cockatrice"),
+            ("see [the *notes*](https://example.com/) here", "see the notes here"),
+            ("![logo](logo.png)", "logo"),
+            ("[a](b (c)) d", "a d"),
+            ("[[a]](b)", "[a]"),
+            ("[x] then [a](b)", "[x] then a"),
+            ("[not a link]", "[not a link]"),
+            ("[a] (b)", "[a] (b)"),
+            ("[a](unclosed", "[a](unclosed"),
+            ("[unclosed", "[unclosed"),
+            // unclosed or literal emphasis markers
+            ("an *unclosed emphasis", "an unclosed emphasis"),
+            ("2 * 3 = 6", "2 * 3 = 6"),
+            ("a ** b", "a ** b"),
+            ("*", "*")
+        ] {
+            assert_eq!(inline(markdown), expected, "converting {:?}", markdown);
+        }
+    }
+
+    #[test]
+    fn lists() {
+        assert_eq!(blocks("* star\n- dash\n+ plus\n1. one\n10. ten\n   continued\n1.5 is not a list item\n-not either"), vec![
+            Block::ListItem("star".to_owned()),
+            Block::ListItem("dash".to_owned()),
+            Block::ListItem("plus".to_owned()),
+            Block::ListItem("one".to_owned()),
+            Block::ListItem("ten continued".to_owned()),
+            Block::Paragraph("1.5 is not a list item -not either".to_owned())
+        ]);
+    }
+
+    #[test]
+    fn release_notes() {
+        assert_eq!(blocks("# Lore Seeker Desktop 0.3\n\nThis release adds *offline search*\nand fixes bugs.\n\n## Changes\n\n* Search [cards](https://lore-seeker.cards/) offline\n* Fix `search` history\n\n```\nlore-seeker-search t:elf\n```\n"), vec![
+            Block::Heading("Lore Seeker Desktop 0.3".to_owned()),
+            Block::Paragraph("This release adds offline search and fixes bugs.".to_owned()),
+            Block::Heading("Changes".to_owned()),
+            Block::ListItem("Search cards offline".to_owned()),
+            Block::ListItem("Fix search history".to_owned()),
+            Block::Paragraph("lore-seeker-search t:elf".to_owned())
+        ]);
+    }
+}
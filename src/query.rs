@@ -0,0 +1,555 @@
+//! Search queries in a subset of the syntax used by Lore Seeker and Scryfall.
+//!
+//! Supported are bare words and quoted strings (matching the card name), `t:` (type line), `o:` (rules text), `c:` (colors), `cmc` (converted mana cost), and `e:` or `s:` (set code), combined with implicit “and”, `or`, `-` for negation, and parentheses.
+
+use std::{
+    fmt,
+    iter::Peekable,
+    ops::Range,
+    str::CharIndices
+};
+
+/// How a property of a card is compared to the value in the query.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Comparison {
+    /// `<`
+    Lt,
+    /// `<=`
+    Le,
+    /// `=`, or `:` for numbers
+    Eq,
+    /// `>=`, or `:` for colors
+    Ge,
+    /// `>`
+    Gt
+}
+
+impl Comparison {
+    /// Compares two numbers.
+    pub fn compare(&self, lhs: f64, rhs: f64) -> bool {
+        match *self {
+            Comparison::Lt => lhs < rhs,
+            Comparison::Le => lhs <= rhs,
+            Comparison::Eq => lhs == rhs,
+            Comparison::Ge => lhs >= rhs,
+            Comparison::Gt => lhs > rhs
+        }
+    }
+}
+
+/// A set of colors, stored as a bit set in WUBRG order.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Colors(u8);
+
+/// The color letters, in WUBRG order.
+static COLOR_LETTERS: [char; 5] = ['W', 'U', 'B', 'R', 'G'];
+
+impl Colors {
+    /// Parses a string of color letters like `WU`, ignoring case. `C` stands for colorless and can't be combined with other colors. Returns `None` if there are any other characters.
+    pub fn from_letters(letters: &str) -> Option<Colors> {
+        if letters.eq_ignore_ascii_case("c") { return Some(Colors::default()); }
+        let mut colors = Colors::default();
+        for c in letters.chars() {
+            let idx = COLOR_LETTERS.iter().position(|&letter| letter.eq_ignore_ascii_case(&c))?;
+            colors.0 |= 1 << idx;
+        }
+        Some(colors)
+    }
+
+    /// Whether these colors are the same as or a superset of `other`.
+    pub fn contains(&self, other: Colors) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// Whether these colors are the same as or a subset of `other`.
+    pub fn is_subset(&self, other: Colors) -> bool {
+        other.contains(*self)
+    }
+
+    /// Compares these colors with `other` as sets.
+    pub fn compare(&self, cmp: Comparison, other: Colors) -> bool {
+        match cmp {
+            Comparison::Lt => self.is_subset(other) && *self != other,
+            Comparison::Le => self.is_subset(other),
+            Comparison::Eq => *self == other,
+            Comparison::Ge => self.contains(other),
+            Comparison::Gt => self.contains(other) && *self != other
+        }
+    }
+}
+
+impl fmt::Display for Colors {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.0 == 0 { return write!(f, "C"); }
+        for (idx, letter) in COLOR_LETTERS.iter().enumerate() {
+            if self.0 & (1 << idx) != 0 { write!(f, "{}", letter)?; }
+        }
+        Ok(())
+    }
+}
+
+/// A condition on a single property of a card.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Filter {
+    /// The card name contains this text, ignoring case.
+    Name(String),
+    /// The type line contains this text, ignoring case.
+    Type(String),
+    /// The rules text contains this text, ignoring case. `~` stands for the card's name.
+    Oracle(String),
+    /// The card's colors compare to these colors as sets.
+    Color(Comparison, Colors),
+    /// The converted mana cost compares to this number.
+    Cmc(Comparison, f64),
+    /// The card was printed in the set with this code, ignoring case.
+    Set(String)
+}
+
+/// A parsed search query.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Query {
+    /// All of the subqueries match. An empty list matches every card.
+    And(Vec<Query>),
+    /// Any of the subqueries matches.
+    Or(Vec<Query>),
+    /// The subquery doesn't match.
+    Not(Box<Query>),
+    /// A condition on a card property.
+    Filter(Filter)
+}
+
+/// The kind of a `ParseError`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// A `(` without matching `)`.
+    UnclosedParen,
+    /// A `)` without matching `(`.
+    UnexpectedCloseParen,
+    /// A `"` without matching `"`.
+    UnclosedQuote,
+    /// A keyword like `t:` without a value, or `-` or `or` without anything to apply it to.
+    MissingValue,
+    /// A keyword which is not supported.
+    UnknownKeyword(String),
+    /// The comparison can't be used with this keyword, e.g. `t>`.
+    UnsupportedComparison(String),
+    /// The value is not valid for the keyword, e.g. `cmc:x`.
+    InvalidValue(String)
+}
+
+/// An error that can occur while parsing a query.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    /// What went wrong.
+    pub kind: ErrorKind,
+    /// The byte range of the query text where the error is, for highlighting. Empty at the end of the query if something is missing there.
+    pub span: Range<usize>
+}
+
+impl ParseError {
+    fn new(kind: ErrorKind, span: Range<usize>) -> ParseError {
+        ParseError { kind, span }
+    }
+
+    /// Whether this is an error in the structure of the query rather than in a keyword or value.
+    ///
+    /// Lore Seeker supports more keywords and values than the offline search, so only syntax errors make a query unusable for an online search.
+    pub fn is_syntax_error(&self) -> bool {
+        match self.kind {
+            ErrorKind::UnclosedParen | ErrorKind::UnexpectedCloseParen | ErrorKind::UnclosedQuote | ErrorKind::MissingValue => true,
+            ErrorKind::UnknownKeyword(_) | ErrorKind::UnsupportedComparison(_) | ErrorKind::InvalidValue(_) => false
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.kind {
+            ErrorKind::UnclosedParen => write!(f, "missing closing parenthesis"),
+            ErrorKind::UnexpectedCloseParen => write!(f, "unexpected closing parenthesis"),
+            ErrorKind::UnclosedQuote => write!(f, "missing closing quote"),
+            ErrorKind::MissingValue => write!(f, "missing search term"),
+            ErrorKind::UnknownKeyword(ref keyword) => write!(f, "unknown keyword {:?}", keyword),
+            ErrorKind::UnsupportedComparison(ref keyword) => write!(f, "{:?} can only be used with \":\"", keyword),
+            ErrorKind::InvalidValue(ref value) => write!(f, "invalid value {:?}", value)
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    OpenParen,
+    CloseParen,
+    Minus,
+    Or,
+    /// A bare word or quoted string.
+    Word(String),
+    /// A keyword, its comparison operator as written, and its value.
+    Keyword(String, String, String)
+}
+
+struct Lexer<'a> {
+    text: &'a str,
+    chars: Peekable<CharIndices<'a>>
+}
+
+impl<'a> Lexer<'a> {
+    fn new(text: &'a str) -> Lexer<'a> {
+        Lexer { text, chars: text.char_indices().peekable() }
+    }
+
+    /// The byte position of the next character.
+    fn pos(&mut self) -> usize {
+        let len = self.text.len();
+        self.chars.peek().map_or(len, |&(idx, _)| idx)
+    }
+
+    /// Reads a quoted string starting at `start`, with the opening quote already consumed.
+    fn quoted(&mut self, start: usize) -> Result<String, ParseError> {
+        let mut value = String::default();
+        loop {
+            match self.chars.next() {
+                Some((_, '"')) => { return Ok(value); }
+                Some((_, '\\')) => if let Some((_, c)) = self.chars.next() { value.push(c); },
+                Some((_, c)) => { value.push(c); }
+                None => { return Err(ParseError::new(ErrorKind::UnclosedQuote, start..self.text.len())); }
+            }
+        }
+    }
+
+    /// Reads characters until whitespace, a parenthesis, or the end of the query.
+    fn bare(&mut self) -> String {
+        let mut value = String::default();
+        while let Some(&(_, c)) = self.chars.peek() {
+            if c.is_whitespace() || c == '(' || c == ')' { break; }
+            value.push(c);
+            self.chars.next();
+        }
+        value
+    }
+
+    fn tokens(mut self) -> Result<Vec<(Token, Range<usize>)>, ParseError> {
+        let mut tokens = Vec::default();
+        while let Some(&(start, c)) = self.chars.peek() {
+            let token = match c {
+                c if c.is_whitespace() => { self.chars.next(); continue; }
+                '(' => { self.chars.next(); Token::OpenParen }
+                ')' => { self.chars.next(); Token::CloseParen }
+                '-' => { self.chars.next(); Token::Minus }
+                '"' => {
+                    self.chars.next();
+                    Token::Word(self.quoted(start)?)
+                }
+                _ => {
+                    let rest = &self.text[start..];
+                    let key_len = rest.find(|c: char| !c.is_ascii_alphabetic()).unwrap_or(rest.len());
+                    let op = ["<=", ">=", ":", "=", "<", ">"].iter().find(|op| key_len > 0 && rest[key_len..].starts_with(**op));
+                    if let Some(op) = op {
+                        for _ in 0..key_len + op.len() { self.chars.next(); }
+                        let value = if self.chars.peek().map_or(false, |&(_, c)| c == '"') {
+                            let quote_start = self.pos();
+                            self.chars.next();
+                            self.quoted(quote_start)?
+                        } else {
+                            self.bare()
+                        };
+                        Token::Keyword(rest[..key_len].to_ascii_lowercase(), op.to_string(), value)
+                    } else {
+                        let word = self.bare();
+                        if word.eq_ignore_ascii_case("or") { Token::Or } else { Token::Word(word) }
+                    }
+                }
+            };
+            tokens.push((token, start..self.pos()));
+        }
+        Ok(tokens)
+    }
+}
+
+struct Parser {
+    tokens: Peekable<std::vec::IntoIter<(Token, Range<usize>)>>,
+    /// The length of the query text, used as the position of errors at the end.
+    len: usize
+}
+
+impl Parser {
+    fn peek(&mut self) -> Option<&Token> {
+        self.tokens.peek().map(|(token, _)| token)
+    }
+
+    /// The span of the next token, or an empty span at the end of the query.
+    fn peek_span(&mut self) -> Range<usize> {
+        let len = self.len;
+        self.tokens.peek().map_or(len..len, |(_, span)| span.clone())
+    }
+
+    fn or(&mut self) -> Result<Query, ParseError> {
+        let mut alternatives = vec![self.and()?];
+        while self.peek() == Some(&Token::Or) {
+            let (_, or_span) = self.tokens.next().expect("just peeked");
+            if self.peek().map_or(true, |token| *token == Token::CloseParen || *token == Token::Or) {
+                return Err(ParseError::new(ErrorKind::MissingValue, or_span));
+            }
+            alternatives.push(self.and()?);
+        }
+        Ok(if alternatives.len() == 1 { alternatives.pop().expect("just checked") } else { Query::Or(alternatives) })
+    }
+
+    fn and(&mut self) -> Result<Query, ParseError> {
+        let mut conditions = Vec::default();
+        loop {
+            match self.peek() {
+                None | Some(Token::CloseParen) | Some(Token::Or) => { break; }
+                Some(_) => { conditions.push(self.unary()?); }
+            }
+        }
+        if conditions.is_empty() { return Err(ParseError::new(ErrorKind::MissingValue, self.peek_span())); }
+        Ok(if conditions.len() == 1 { conditions.pop().expect("just checked") } else { Query::And(conditions) })
+    }
+
+    fn unary(&mut self) -> Result<Query, ParseError> {
+        let (token, span) = self.tokens.next().expect("called with a token available");
+        match token {
+            Token::Minus => match self.peek() {
+                None | Some(Token::CloseParen) | Some(Token::Or) => Err(ParseError::new(ErrorKind::MissingValue, span)),
+                Some(_) => Ok(Query::Not(Box::new(self.unary()?)))
+            },
+            Token::OpenParen => {
+                let query = self.or()?;
+                match self.tokens.next() {
+                    Some((Token::CloseParen, _)) => Ok(query),
+                    _ => Err(ParseError::new(ErrorKind::UnclosedParen, span))
+                }
+            }
+            Token::Word(word) => Ok(Query::Filter(Filter::Name(word))),
+            Token::Keyword(keyword, op, value) => Ok(Query::Filter(filter(&keyword, &op, value).map_err(|kind| ParseError::new(kind, span))?)),
+            Token::CloseParen | Token::Or => unreachable!("handled by Parser::and")
+        }
+    }
+}
+
+/// Interprets a keyword term like `t:elf`.
+fn filter(keyword: &str, op: &str, value: String) -> Result<Filter, ErrorKind> {
+    if value.is_empty() { return Err(ErrorKind::MissingValue); }
+    let cmp = match op {
+        "<" => Comparison::Lt,
+        "<=" => Comparison::Le,
+        "=" => Comparison::Eq,
+        ">=" => Comparison::Ge,
+        ">" => Comparison::Gt,
+        _ => match keyword {
+            "c" | "color" => Comparison::Ge,
+            _ => Comparison::Eq
+        }
+    };
+    let text_only = |filter: fn(String) -> Filter| if op == ":" || op == "=" {
+        Ok(filter(value.to_lowercase()))
+    } else {
+        Err(ErrorKind::UnsupportedComparison(keyword.to_owned()))
+    };
+    match keyword {
+        "t" | "type" => text_only(Filter::Type),
+        "o" | "oracle" => text_only(Filter::Oracle),
+        "e" | "s" | "set" => text_only(Filter::Set),
+        "c" | "color" => {
+            let colors = Colors::from_letters(&value).ok_or_else(|| ErrorKind::InvalidValue(value.clone()))?;
+            // `c:c` means colorless, not “at least no colors”
+            Ok(Filter::Color(if colors == Colors::default() && op == ":" { Comparison::Eq } else { cmp }, colors))
+        }
+        "cmc" => match value.parse::<f64>() {
+            Ok(cmc) if cmc.is_finite() => Ok(Filter::Cmc(cmp, cmc)),
+            _ => Err(ErrorKind::InvalidValue(value.clone())) // `nan` and `inf` parse as floats but never match
+        },
+        _ => Err(ErrorKind::UnknownKeyword(keyword.to_owned()))
+    }
+}
+
+/// Parses a search query. An empty query matches all cards.
+///
+/// If the query is invalid, the first error is returned along with its location in the query.
+pub fn parse(text: &str) -> Result<Query, ParseError> {
+    let tokens = Lexer::new(text).tokens()?;
+    if tokens.is_empty() { return Ok(Query::And(Vec::default())); }
+    let mut parser = Parser { tokens: tokens.into_iter().peekable(), len: text.len() };
+    let query = parser.or()?;
+    match parser.tokens.next() {
+        None => Ok(query),
+        Some((_, span)) => Err(ParseError::new(ErrorKind::UnexpectedCloseParen, span)) // `or` stops only at `)` or the end
+    }
+}
+
+impl fmt::Display for Comparison {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Comparison::Lt => write!(f, "<"),
+            Comparison::Le => write!(f, "<="),
+            Comparison::Eq => write!(f, "="),
+            Comparison::Ge => write!(f, ">="),
+            Comparison::Gt => write!(f, ">")
+        }
+    }
+}
+
+/// Writes a value so that the lexer reads it back unchanged, quoting it if necessary.
+fn write_value(f: &mut fmt::Formatter, value: &str, is_name: bool) -> fmt::Result {
+    let needs_quotes = value.is_empty()
+        || value.contains(|c: char| c.is_whitespace() || c == '(' || c == ')' || c == '"' || c == '\\')
+        || value.starts_with('-')
+        || is_name && (value.eq_ignore_ascii_case("or") || value.contains(|c: char| c == ':' || c == '<' || c == '>' || c == '='));
+    if needs_quotes {
+        write!(f, "\"")?;
+        for c in value.chars() {
+            if c == '"' || c == '\\' { write!(f, "\\")?; }
+            write!(f, "{}", c)?;
+        }
+        write!(f, "\"")
+    } else {
+        write!(f, "{}", value)
+    }
+}
+
+impl fmt::Display for Filter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Filter::Name(ref name) => write_value(f, name, true),
+            Filter::Type(ref type_line) => { write!(f, "t:")?; write_value(f, type_line, false) }
+            Filter::Oracle(ref text) => { write!(f, "o:")?; write_value(f, text, false) }
+            Filter::Color(Comparison::Ge, colors) if colors != Colors::default() => write!(f, "c:{}", colors),
+            Filter::Color(Comparison::Eq, colors) if colors == Colors::default() => write!(f, "c:C"),
+            Filter::Color(cmp, colors) => write!(f, "c{}{}", cmp, colors),
+            Filter::Cmc(cmp, cmc) => write!(f, "cmc{}{}", cmp, cmc),
+            Filter::Set(ref code) => { write!(f, "e:")?; write_value(f, code, false) }
+        }
+    }
+}
+
+/// Displays the query in canonical form: keywords are written in their shortest form, values are only quoted if necessary, and parentheses are only used where required. Parsing the result gives back the same query.
+impl fmt::Display for Query {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Query::And(ref queries) => {
+                for (i, query) in queries.iter().enumerate() {
+                    if i > 0 { write!(f, " ")?; }
+                    match *query {
+                        Query::Or(_) | Query::And(_) => write!(f, "({})", query)?,
+                        _ => write!(f, "{}", query)?
+                    }
+                }
+                Ok(())
+            }
+            Query::Or(ref queries) => {
+                for (i, query) in queries.iter().enumerate() {
+                    if i > 0 { write!(f, " or ")?; }
+                    match *query {
+                        Query::Or(_) => write!(f, "({})", query)?,
+                        _ => write!(f, "{}", query)?
+                    }
+                }
+                Ok(())
+            }
+            Query::Not(ref query) => match **query {
+                Query::And(_) | Query::Or(_) => write!(f, "-({})", query),
+                _ => write!(f, "-{}", query)
+            },
+            Query::Filter(ref filter) => filter.fmt(f)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn f(filter: Filter) -> Query {
+        Query::Filter(filter)
+    }
+
+    fn colors(letters: &str) -> Colors {
+        Colors::from_letters(letters).expect("invalid color letters")
+    }
+
+    fn error_kind(text: &str) -> ErrorKind {
+        parse(text).expect_err("query should be invalid").kind
+    }
+
+    #[test]
+    fn text_keywords() {
+        assert_eq!(parse("t:Elf"), Ok(f(Filter::Type("elf".to_owned()))));
+        assert_eq!(parse("type=elf"), Ok(f(Filter::Type("elf".to_owned()))));
+        assert_eq!(parse("o:\"Draw a card\""), Ok(f(Filter::Oracle("draw a card".to_owned()))));
+        assert_eq!(parse("oracle:~"), Ok(f(Filter::Oracle("~".to_owned()))));
+        assert_eq!(error_kind("t>elf"), ErrorKind::UnsupportedComparison("t".to_owned()));
+        assert_eq!(error_kind("o:"), ErrorKind::MissingValue);
+    }
+
+    #[test]
+    fn color() {
+        assert_eq!(parse("c:wu"), Ok(f(Filter::Color(Comparison::Ge, colors("WU")))));
+        assert_eq!(parse("c:c"), Ok(f(Filter::Color(Comparison::Eq, Colors::default()))));
+        assert_eq!(parse("c>=r"), Ok(f(Filter::Color(Comparison::Ge, colors("R")))));
+        assert_eq!(parse("c<wu"), Ok(f(Filter::Color(Comparison::Lt, colors("WU")))));
+        assert_eq!(parse("color=g"), Ok(f(Filter::Color(Comparison::Eq, colors("G")))));
+        assert_eq!(error_kind("c:x"), ErrorKind::InvalidValue("x".to_owned()));
+        assert!(colors("WU").compare(Comparison::Ge, colors("W")));
+        assert!(!colors("WU").compare(Comparison::Lt, colors("WU")));
+        assert!(Colors::default().compare(Comparison::Le, colors("WU")));
+        assert!(colors("WUB").compare(Comparison::Gt, colors("WU")));
+    }
+
+    #[test]
+    fn cmc() {
+        assert_eq!(parse("cmc<2"), Ok(f(Filter::Cmc(Comparison::Lt, 2.0))));
+        assert_eq!(parse("cmc<=2"), Ok(f(Filter::Cmc(Comparison::Le, 2.0))));
+        assert_eq!(parse("cmc:2"), Ok(f(Filter::Cmc(Comparison::Eq, 2.0))));
+        assert_eq!(parse("cmc=2.5"), Ok(f(Filter::Cmc(Comparison::Eq, 2.5))));
+        assert_eq!(parse("cmc>=2"), Ok(f(Filter::Cmc(Comparison::Ge, 2.0))));
+        assert_eq!(parse("cmc>2"), Ok(f(Filter::Cmc(Comparison::Gt, 2.0))));
+        assert_eq!(error_kind("cmc:x"), ErrorKind::InvalidValue("x".to_owned()));
+    }
+
+    #[test]
+    fn set() {
+        assert_eq!(parse("e:VLN"), Ok(f(Filter::Set("vln".to_owned()))));
+        assert_eq!(parse("s:vln"), Ok(f(Filter::Set("vln".to_owned()))));
+        assert_eq!(parse("set:vln"), Ok(f(Filter::Set("vln".to_owned()))));
+        assert_eq!(error_kind("foo:bar"), ErrorKind::UnknownKeyword("foo".to_owned()));
+    }
+
+    #[test]
+    fn structure() {
+        assert_eq!(parse(""), Ok(Query::And(Vec::default())));
+        assert_eq!(parse("grove warden"), Ok(Query::And(vec![f(Filter::Name("grove".to_owned())), f(Filter::Name("warden".to_owned()))])));
+        assert_eq!(parse("\"grove warden\""), Ok(f(Filter::Name("grove warden".to_owned()))));
+        assert_eq!(parse("elf OR goblin"), Ok(Query::Or(vec![f(Filter::Name("elf".to_owned())), f(Filter::Name("goblin".to_owned()))])));
+        assert_eq!(parse("-t:land"), Ok(Query::Not(Box::new(f(Filter::Type("land".to_owned()))))));
+        assert_eq!(parse("(t:elf or (c:g cmc>2)) -e:vln"), Ok(Query::And(vec![
+            Query::Or(vec![
+                f(Filter::Type("elf".to_owned())),
+                Query::And(vec![f(Filter::Color(Comparison::Ge, colors("G"))), f(Filter::Cmc(Comparison::Gt, 2.0))])
+            ]),
+            Query::Not(Box::new(f(Filter::Set("vln".to_owned()))))
+        ])));
+    }
+
+    #[test]
+    fn syntax_errors() {
+        assert_eq!(parse("(t:elf"), Err(ParseError::new(ErrorKind::UnclosedParen, 0..1)));
+        assert_eq!(parse("t:elf)"), Err(ParseError::new(ErrorKind::UnexpectedCloseParen, 5..6)));
+        assert_eq!(parse("\"grove"), Err(ParseError::new(ErrorKind::UnclosedQuote, 0..6)));
+        assert_eq!(error_kind("elf or"), ErrorKind::MissingValue);
+        assert_eq!(error_kind("or elf"), ErrorKind::MissingValue);
+        assert_eq!(error_kind("elf -"), ErrorKind::MissingValue);
+        assert_eq!(parse("t:elf cmc>inf"), Err(ParseError::new(ErrorKind::InvalidValue("inf".to_owned()), 6..13)));
+        assert_eq!(error_kind("cmc=NaN"), ErrorKind::InvalidValue("NaN".to_owned()));
+        assert!(parse("(t:elf").expect_err("query should be invalid").is_syntax_error());
+        assert!(!parse("foo:bar").expect_err("query should be invalid").is_syntax_error());
+    }
+
+    #[test]
+    fn display_round_trip() {
+        for text in &["grove warden", "\"grove warden\"", "t:elf or t:goblin", "-(c:c or cmc>=3) e:vln", "\"or\" \"t:elf\"", "c<wu c:c o:\"~ enters\"", "((elf or goblin) warrior) or land"] {
+            let query = parse(text).expect("failed to parse query");
+            assert_eq!(parse(&query.to_string()), Ok(query.clone()), "{} was displayed as {}", text, query);
+        }
+    }
+}
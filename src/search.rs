@@ -0,0 +1,197 @@
+//! Searching the card database synced from Lore Seeker without an internet connection.
+
+use crate::{
+    lore_seeker::{
+        self,
+        PAGE_SIZE,
+        SearchPage
+    },
+    query::{
+        Colors,
+        Filter,
+        Query
+    },
+    trice::{
+        carddb::{
+            self,
+            Card,
+            CardDatabase
+        },
+        paths::Paths,
+        sync
+    }
+};
+
+/// A card with its searchable properties normalized for matching.
+struct Entry {
+    card: Card,
+    name: String,
+    type_line: String,
+    text: String,
+    colors: Colors,
+    cmc: f64,
+    sets: Vec<String>
+}
+
+impl Entry {
+    fn new(card: Card) -> Entry {
+        let name = card.name.to_lowercase();
+        Entry {
+            type_line: card.type_line().unwrap_or_default().to_lowercase(),
+            text: card.text.to_lowercase().replace(&name, "~"),
+            colors: card.colors().and_then(Colors::from_letters).unwrap_or_default(),
+            cmc: card.cmc().and_then(|cmc| cmc.parse().ok()).unwrap_or(0.0),
+            sets: card.sets.iter().map(|printing| printing.code.to_lowercase()).collect(),
+            name,
+            card
+        }
+    }
+
+    fn matches(&self, query: &Query) -> bool {
+        match *query {
+            Query::And(ref queries) => queries.iter().all(|query| self.matches(query)),
+            Query::Or(ref queries) => queries.iter().any(|query| self.matches(query)),
+            Query::Not(ref query) => !self.matches(query),
+            Query::Filter(Filter::Name(ref name)) => self.name.contains(&name.to_lowercase()),
+            Query::Filter(Filter::Type(ref type_line)) => self.type_line.contains(type_line),
+            Query::Filter(Filter::Oracle(ref text)) => self.text.contains(&text.replace(&self.name, "~")),
+            Query::Filter(Filter::Color(cmp, colors)) => self.colors.compare(cmp, colors),
+            Query::Filter(Filter::Cmc(cmp, cmc)) => cmp.compare(self.cmc, cmc),
+            Query::Filter(Filter::Set(ref code)) => self.sets.iter().any(|set| set == code)
+        }
+    }
+}
+
+/// An index of a card database for searching. Tokens are not included.
+pub struct Index {
+    entries: Vec<Entry>
+}
+
+impl Index {
+    /// Indexes the given card database.
+    pub fn new(db: CardDatabase) -> Index {
+        let mut entries = db.cards.into_iter().filter(|card| !card.token).map(Entry::new).collect::<Vec<_>>();
+        entries.sort_by(|e1, e2| e1.name.cmp(&e2.name).then_with(|| e1.card.name.cmp(&e2.card.name)));
+        Index { entries }
+    }
+
+    /// Indexes the card database previously synced into the given Cockatrice data directory, see `trice::sync::sync`.
+    pub fn open(paths: &Paths) -> Result<Index, carddb::Error> {
+        Ok(Index::new(CardDatabase::open(paths.customsets_dir().join(sync::FILENAME))?))
+    }
+
+    /// Returns the cards matching the query, sorted by name.
+    pub fn search<'a>(&'a self, query: &'a Query) -> impl Iterator<Item = &'a Card> + 'a {
+        self.entries.iter().filter(move |entry| entry.matches(query)).map(|entry| &entry.card)
+    }
+
+    /// Returns the given page of results, starting at 1, in the same format as `lore_seeker::Server::search`.
+    pub fn search_page(&self, query: &Query, page: usize) -> SearchPage {
+        let results = self.search(query).collect::<Vec<_>>();
+        SearchPage {
+            cards: results.iter().skip(page.saturating_sub(1) * PAGE_SIZE).take(PAGE_SIZE).map(|card| to_result(card)).collect(),
+            page,
+            total_cards: results.len(),
+            page_size: PAGE_SIZE
+        }
+    }
+}
+
+/// Converts a card to a search result, using its first printing.
+fn to_result(card: &Card) -> lore_seeker::Card {
+    let printing = card.sets.first();
+    lore_seeker::Card {
+        name: card.name.clone(),
+        set: printing.map(|printing| printing.code.clone()).unwrap_or_default(),
+        number: printing.and_then(|printing| printing.num()).map(str::to_owned),
+        mana_cost: card.manacost().map(str::to_owned),
+        type_line: card.type_line().unwrap_or_default().to_owned(),
+        oracle_text: card.text.clone(),
+        image_url: printing.and_then(|printing| printing.picurl()).map(str::to_owned)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        query,
+        trice::carddb::Printing
+    };
+    use super::*;
+
+    fn card(name: &str, type_line: &str, colors: &str, cmc: &str, text: &str, set: &str) -> Card {
+        let mut card = Card::new(name);
+        card.set_prop("type", type_line);
+        if !colors.is_empty() { card.set_prop("colors", colors); }
+        card.set_prop("cmc", cmc);
+        card.text = text.to_owned();
+        card.sets.push(Printing { code: set.to_owned(), attributes: Vec::default() });
+        card
+    }
+
+    fn index() -> Index {
+        let mut token = card("Elf Warrior", "Token Creature — Elf Warrior", "G", "0", "", "TVLN");
+        token.token = true;
+        Index::new(CardDatabase {
+            cards: vec![
+                card("Grove Warden", "Creature — Elf Warrior", "G", "2", "Vigilance\nWhen Grove Warden enters the battlefield, you gain 2 life.", "VLN"),
+                card("Shrine of the Lost", "Land", "", "0", "Shrine of the Lost enters the battlefield tapped.", "VLN"),
+                card("Ember Adept", "Creature — Human Wizard", "R", "3", "Flying", "XLN"),
+                card("Azorius Envoy", "Creature — Human Advisor", "WU", "4", "When Azorius Envoy enters the battlefield, draw a card.", "XLN"),
+                token
+            ],
+            ..CardDatabase::default()
+        })
+    }
+
+    fn names(index: &Index, query: &str) -> Vec<String> {
+        let query = query::parse(query).expect("failed to parse query");
+        index.search(&query).map(|card| card.name.clone()).collect()
+    }
+
+    #[test]
+    fn excludes_tokens() {
+        let index = index();
+        assert_eq!(names(&index, ""), vec!["Azorius Envoy", "Ember Adept", "Grove Warden", "Shrine of the Lost"]);
+        assert_eq!(names(&index, "t:elf"), vec!["Grove Warden"]);
+        assert!(names(&index, "s:tvln").is_empty());
+    }
+
+    #[test]
+    fn text_filters() {
+        let index = index();
+        assert_eq!(names(&index, "warden"), vec!["Grove Warden"]);
+        assert_eq!(names(&index, "\"of the\""), vec!["Shrine of the Lost"]);
+        assert_eq!(names(&index, "t:human"), vec!["Azorius Envoy", "Ember Adept"]);
+        assert_eq!(names(&index, "o:flying"), vec!["Ember Adept"]);
+        assert_eq!(names(&index, "o:\"~ enters\""), vec!["Azorius Envoy", "Grove Warden", "Shrine of the Lost"]);
+        assert_eq!(names(&index, "o:\"Grove Warden enters\""), vec!["Grove Warden"]);
+    }
+
+    #[test]
+    fn color_and_cmc() {
+        let index = index();
+        assert_eq!(names(&index, "c:c"), vec!["Shrine of the Lost"]);
+        assert_eq!(names(&index, "c:u"), vec!["Azorius Envoy"]);
+        assert_eq!(names(&index, "c>=r"), vec!["Ember Adept"]);
+        assert_eq!(names(&index, "c<wu"), vec!["Shrine of the Lost"]);
+        assert_eq!(names(&index, "c<=wu"), vec!["Azorius Envoy", "Shrine of the Lost"]);
+        assert_eq!(names(&index, "cmc<2"), vec!["Shrine of the Lost"]);
+        assert_eq!(names(&index, "cmc=2"), vec!["Grove Warden"]);
+        assert_eq!(names(&index, "cmc>=3"), vec!["Azorius Envoy", "Ember Adept"]);
+    }
+
+    #[test]
+    fn sets_and_structure() {
+        let index = index();
+        assert_eq!(names(&index, "e:xln"), vec!["Azorius Envoy", "Ember Adept"]);
+        assert_eq!(names(&index, "-e:xln"), vec!["Grove Warden", "Shrine of the Lost"]);
+        assert_eq!(names(&index, "warden or t:land"), vec!["Grove Warden", "Shrine of the Lost"]);
+        assert_eq!(names(&index, "(c:g or c:r) cmc>2"), vec!["Ember Adept"]);
+        let page = index.search_page(&query::parse("t:creature").expect("failed to parse query"), 1);
+        assert_eq!(page.total_cards, 3);
+        assert_eq!(page.cards[0].name, "Azorius Envoy");
+        assert_eq!(page.cards[0].set, "XLN");
+        assert!(!page.has_next());
+    }
+}
@@ -0,0 +1,191 @@
+//! Signed release manifests, which let the self-updater verify that a release was published by the maintainer.
+
+use std::{
+    collections::BTreeMap,
+    convert::TryInto,
+    fmt
+};
+use ed25519_dalek::{
+    Signature,
+    Signer,
+    SigningKey,
+    Verifier,
+    VerifyingKey
+};
+use serde_derive::{
+    Deserialize,
+    Serialize
+};
+use wrapped_enum::wrapped_enum;
+use crate::{
+    github::Release,
+    version
+};
+
+/// The name of the release asset containing the `ReleaseManifest` as JSON.
+pub static MANIFEST_NAME: &'static str = "release-manifest.json";
+/// The name of the release asset containing the ed25519 signature of the manifest asset.
+pub static SIGNATURE_NAME: &'static str = "release-manifest.json.sig";
+
+/// An error that can occur while verifying a release manifest.
+#[derive(Debug)]
+pub enum OtherError {
+    /// The release has no manifest or no signature.
+    MissingManifest,
+    /// This build of Lore Seeker Desktop was compiled without a public key, so it can't verify releases.
+    MissingPublicKey,
+    /// The signature or key has the wrong length.
+    Malformed
+}
+
+wrapped_enum! {
+    /// An error that can occur while verifying a release manifest.
+    #[derive(Debug)]
+    pub enum Error {
+        #[allow(missing_docs)]
+        Json(serde_json::Error),
+        #[allow(missing_docs)]
+        Other(OtherError),
+        #[allow(missing_docs)]
+        Reqwest(reqwest::Error),
+        /// The signature does not match the manifest.
+        Signature(ed25519_dalek::SignatureError)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::Json(ref e) => e.fmt(f),
+            Error::Other(OtherError::MissingManifest) => write!(f, "The release is not signed."),
+            Error::Other(OtherError::MissingPublicKey) => write!(f, "This build of Lore Seeker Desktop cannot verify updates."),
+            Error::Other(OtherError::Malformed) => write!(f, "The release signature is malformed."),
+            Error::Reqwest(ref e) => e.fmt(f),
+            Error::Signature(_) => write!(f, "The release signature is invalid.")
+        }
+    }
+}
+
+/// Information about a release, signed by the maintainer.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReleaseManifest {
+    /// The version number of the release, without the `v` prefix.
+    pub version: String,
+    /// The hash of the commit the release was built from.
+    pub commit: String,
+    /// The lowercase hex SHA-256 digests of the release assets, keyed by asset name.
+    pub assets: BTreeMap<String, String>
+}
+
+impl ReleaseManifest {
+    /// Serializes the manifest and signs it with the given key. Returns the contents of the manifest and signature assets.
+    pub fn sign(&self, key: &SigningKey) -> Result<(Vec<u8>, Vec<u8>), serde_json::Error> {
+        let json = serde_json::to_vec_pretty(self)?;
+        let signature = key.sign(&json);
+        Ok((json, signature.to_bytes().to_vec()))
+    }
+
+    /// Downloads the manifest of the given release and verifies its signature using the public key embedded at compile time.
+    pub fn fetch_verified(client: &reqwest::Client, release: &Release) -> Result<ReleaseManifest, Error> {
+        let public_key = version::RELEASE_PUBLIC_KEY.ok_or(OtherError::MissingPublicKey)?;
+        let download = |name: &str| -> Result<Vec<u8>, Error> {
+            let asset = release.assets.iter().find(|asset| asset.name == name).ok_or(OtherError::MissingManifest)?;
+            let mut buf = Vec::default();
+            client.get(&asset.browser_download_url).send()?.error_for_status()?.copy_to(&mut buf)?;
+            Ok(buf)
+        };
+        let json = download(MANIFEST_NAME)?;
+        let signature = download(SIGNATURE_NAME)?;
+        ReleaseManifest::verify(&public_key, &json, &signature)
+    }
+
+    /// Verifies the signature of a serialized manifest and parses it.
+    pub fn verify(public_key: &[u8; 32], json: &[u8], signature: &[u8]) -> Result<ReleaseManifest, Error> {
+        let public_key = VerifyingKey::from_bytes(public_key)?;
+        let signature = Signature::from_bytes(signature.try_into().map_err(|_| OtherError::Malformed)?);
+        public_key.verify(json, &signature)?;
+        Ok(serde_json::from_slice(json)?)
+    }
+}
+
+/// Parses a key from hex, ignoring surrounding whitespace.
+pub fn key_from_hex(hex: &str) -> Option<[u8; 32]> {
+    let hex = hex.trim();
+    if hex.len() != 64 || !hex.chars().all(|c| c.is_ascii_hexdigit()) { return None; } // also rejects signs, which `from_str_radix` would accept
+    let mut key = [0; 32];
+    for (i, byte) in key.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(hex.get(2 * i..2 * i + 2)?, 16).ok()?;
+    }
+    Some(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn manifest() -> ReleaseManifest {
+        ReleaseManifest {
+            version: "0.2.4".to_owned(),
+            commit: "0123456789abcdef0123456789abcdef01234567".to_owned(),
+            assets: vec![("lore-seeker-desktop-win64.exe".to_owned(), "9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08".to_owned())].into_iter().collect()
+        }
+    }
+
+    #[test]
+    fn sign_then_verify() {
+        let key = SigningKey::from_bytes(&[1; 32]);
+        let (json, signature) = manifest().sign(&key).expect("failed to sign manifest");
+        let verified = ReleaseManifest::verify(&key.verifying_key().to_bytes(), &json, &signature).expect("failed to verify manifest");
+        assert_eq!(verified, manifest());
+    }
+
+    #[test]
+    fn tampered_manifest() {
+        let key = SigningKey::from_bytes(&[1; 32]);
+        let (_, signature) = manifest().sign(&key).expect("failed to sign manifest");
+        let (tampered, _) = ReleaseManifest { commit: "fedcba9876543210fedcba9876543210fedcba98".to_owned(), ..manifest() }.sign(&key).expect("failed to sign manifest");
+        match ReleaseManifest::verify(&key.verifying_key().to_bytes(), &tampered, &signature) {
+            Err(Error::Signature(_)) => {}
+            result => panic!("expected signature error, got {:?}", result)
+        }
+    }
+
+    #[test]
+    fn wrong_key() {
+        let (json, signature) = manifest().sign(&SigningKey::from_bytes(&[1; 32])).expect("failed to sign manifest");
+        match ReleaseManifest::verify(&SigningKey::from_bytes(&[2; 32]).verifying_key().to_bytes(), &json, &signature) {
+            Err(Error::Signature(_)) => {}
+            result => panic!("expected signature error, got {:?}", result)
+        }
+    }
+
+    #[test]
+    fn malformed_signature() {
+        let key = SigningKey::from_bytes(&[1; 32]);
+        let (json, signature) = manifest().sign(&key).expect("failed to sign manifest");
+        let public_key = key.verifying_key().to_bytes();
+        for signature in &[&[][..], &signature[..63], &[&signature[..], &[0]].concat()[..]] {
+            match ReleaseManifest::verify(&public_key, &json, signature) {
+                Err(Error::Other(OtherError::Malformed)) => {}
+                result => panic!("expected malformed signature error for {} bytes, got {:?}", signature.len(), result)
+            }
+        }
+        // the right length but not a valid signature
+        assert!(ReleaseManifest::verify(&public_key, &json, &[0xff; 64]).is_err());
+    }
+
+    #[test]
+    fn parse_key() {
+        let hex = "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1F";
+        let mut expected = [0; 32];
+        for (i, byte) in expected.iter_mut().enumerate() { *byte = i as u8; }
+        assert_eq!(key_from_hex(hex), Some(expected));
+        assert_eq!(key_from_hex(&format!("  {}\n", hex)), Some(expected));
+        assert_eq!(key_from_hex(""), None);
+        assert_eq!(key_from_hex(&hex[..62]), None);
+        assert_eq!(key_from_hex(&format!("{}00", hex)), None);
+        assert_eq!(key_from_hex(&hex.replace("0a", "0g")), None);
+        assert_eq!(key_from_hex(&hex.replace("0a", "+a")), None);
+        assert_eq!(key_from_hex(&hex.replace("0a", "é")), None);
+    }
+}
@@ -0,0 +1,82 @@
+//! A minimal HTTP server standing in for GitHub and Lore Seeker in tests.
+
+use std::{
+    io::{
+        self,
+        prelude::*
+    },
+    net::TcpListener,
+    thread::{
+        self,
+        JoinHandle
+    }
+};
+
+/// A request received by the server.
+#[derive(Debug)]
+pub struct Request {
+    /// The request line, e.g. `GET /api/search HTTP/1.1`.
+    pub line: String,
+    /// The header lines, with names lowercased.
+    pub headers: Vec<(String, String)>
+}
+
+impl Request {
+    /// The value of the header with the given name, if present.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.iter().find(|&&(ref header_name, _)| header_name.eq_ignore_ascii_case(name)).map(|&(_, ref value)| &value[..])
+    }
+
+    /// The path requested, including the query string.
+    pub fn path(&self) -> &str {
+        self.line.split(' ').nth(1).unwrap_or_default()
+    }
+}
+
+/// Builds a raw HTTP response with the given status line (e.g. `200 OK`), extra header lines, and body.
+///
+/// `content_length` is sent as the `Content-Length` header. It can be larger than the body to simulate a connection that drops mid-response.
+pub fn response(status: &str, headers: &[&str], content_length: usize, body: &[u8]) -> Vec<u8> {
+    let mut response = format!("HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n", status, content_length);
+    for header in headers {
+        response.push_str(header);
+        response.push_str("\r\n");
+    }
+    response.push_str("\r\n");
+    let mut response = response.into_bytes();
+    response.extend_from_slice(body);
+    response
+}
+
+/// Starts a server on a random local port which answers one connection with each of the given raw responses, in order, then stops.
+///
+/// Returns the base URL of the server, without a trailing slash, and a handle which yields the received requests once all responses have been sent.
+pub fn serve(responses: Vec<Vec<u8>>) -> (String, JoinHandle<Vec<Request>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").expect("failed to bind test server");
+    let base_url = format!("http://{}", listener.local_addr().expect("failed to get test server address"));
+    let handle = thread::spawn(move || responses.into_iter().map(|response| {
+        let (mut stream, _) = listener.accept().expect("failed to accept connection");
+        let request = read_request(&mut stream).expect("failed to read request");
+        stream.write_all(&response).expect("failed to write response");
+        request
+    }).collect());
+    (base_url, handle)
+}
+
+/// Reads a request head. The request body, if any, is ignored.
+fn read_request(stream: &mut impl Read) -> io::Result<Request> {
+    let mut head = Vec::default();
+    let mut byte = [0];
+    while !head.ends_with(b"\r\n\r\n") {
+        if stream.read(&mut byte)? == 0 { return Err(io::ErrorKind::UnexpectedEof.into()); }
+        head.push(byte[0]);
+    }
+    let head = String::from_utf8_lossy(&head);
+    let mut lines = head.split("\r\n").filter(|line| !line.is_empty());
+    let line = lines.next().unwrap_or_default().to_owned();
+    let headers = lines.filter_map(|line| {
+        let mut parts = line.splitn(2, ':');
+        Some((parts.next()?.trim().to_ascii_lowercase(), parts.next()?.trim().to_owned()))
+    }).collect();
+    Ok(Request { line, headers })
+}
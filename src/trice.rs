@@ -3,33 +3,98 @@
 use std::{
     fmt,
     io,
-    process::Command
+    path::{
+        Path,
+        PathBuf
+    }
+};
+#[cfg(any(windows, target_os = "linux"))]
+use std::fs;
+#[cfg(windows)]
+use std::{
+    env,
+    ffi::OsString
 };
+#[cfg(any(windows, target_os = "linux"))]
+use std::process::Command;
+#[cfg(target_os = "linux")]
+use std::{
+    ffi::OsStr,
+    fs::Permissions,
+    os::unix::fs::PermissionsExt,
+    process::Stdio
+};
+#[cfg(any(windows, target_os = "linux"))]
 use itertools::Itertools;
+use semver::Version;
 use wrapped_enum::wrapped_enum;
 use crate::{
-    github::Repo,
+    github::{
+        Release,
+        Repo
+    },
+    util
+};
+#[cfg(any(windows, target_os = "linux"))]
+use crate::{
+    checksum::{
+        self,
+        Manifest
+    },
+    github::ReleaseAsset,
     util::client
 };
+#[cfg(target_os = "linux")]
+use self::paths::Roots;
+use self::probe::Probe;
+
+pub mod carddb;
+pub mod paths;
+pub mod pictures;
+pub mod probe;
+pub mod settings;
+pub mod sync;
+pub mod tokens;
 
-#[cfg(target_arch = "x86")]
+#[cfg(all(windows, target_arch = "x86"))]
 static PLATFORM_SUFFIX: &'static str = "win32.exe";
-#[cfg(target_arch = "x86_64")]
+#[cfg(all(windows, target_arch = "x86_64"))]
 static PLATFORM_SUFFIX: &'static str = "win64.exe";
+#[cfg(target_os = "linux")]
+static PLATFORM_SUFFIX: &'static str = ".AppImage";
 
 /// An error that can occur while installing Cockatrice.
 #[derive(Debug)]
 pub enum OtherError {
+    /// The downloaded file's SHA-256 digest does not match the one published with the release.
+    ChecksumMismatch {
+        /// The digest listed in the release's checksum manifest.
+        expected: String,
+        /// The digest of the downloaded file.
+        actual: String
+    },
     /// The Cockatrice installer exited with an error exit code.
     Installer,
+    /// The user's home directory could not be determined.
+    MissingHomeDir,
     /// The asset for the local platform was not found in the current release.
-    MissingAsset
+    MissingAsset,
+    /// The release doesn't list a SHA-256 checksum for the download, and `InstallOptions::allow_unverified` is not set.
+    MissingChecksum,
+    /// There is no release matching the requested version.
+    MissingRelease,
+    /// The version number could not be determined from the release's tag name.
+    UnparseableTag(String),
+    /// Installing Cockatrice is not supported on this platform.
+    UnsupportedPlatform
 }
 
 wrapped_enum! {
     /// An error that can occur while installing Cockatrice.
     #[derive(Debug)]
     pub enum Error {
+        #[allow(missing_docs)]
+        Download(util::DownloadError),
         #[allow(missing_docs)]
         Io(io::Error),
         #[allow(missing_docs)]
@@ -42,41 +107,431 @@ wrapped_enum! {
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
+            Error::Download(ref e) => e.fmt(f),
             Error::Io(ref e) => e.fmt(f),
+            Error::Other(OtherError::ChecksumMismatch { ref expected, ref actual }) => write!(f, "The Cockatrice download is corrupted (SHA-256 checksum {}, expected {}).", actual, expected),
             Error::Other(OtherError::Installer) => write!(f, "Cockatrice Setup failed."),
+            Error::Other(OtherError::MissingHomeDir) => write!(f, "Could not find your home directory."),
             Error::Other(OtherError::MissingAsset) => write!(f, "Could not find download link for Cockatrice."),
+            Error::Other(OtherError::MissingChecksum) => write!(f, "The Cockatrice release does not include a SHA-256 checksum for the download, so it can't be verified."),
+            Error::Other(OtherError::MissingRelease) => write!(f, "Could not find the requested Cockatrice version."),
+            Error::Other(OtherError::UnparseableTag(ref tag_name)) => write!(f, "Could not determine the Cockatrice version from the release tag {:?}.", tag_name),
+            Error::Other(OtherError::UnsupportedPlatform) => write!(f, "Installing Cockatrice is not supported on this platform."),
             Error::Reqwest(ref e) => e.fmt(f)
         }
     }
 }
 
-/// Downloads and installs Cockatrice using the interactive installer.
-pub fn install(debug: bool) -> Result<(), Error> {
+/// Which release of Cockatrice to install.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InstallVersion {
+    /// The latest full release.
+    LatestStable,
+    /// The newest pre-release. Full releases are skipped even if they're newer.
+    LatestPrerelease,
+    /// The release with the given tag name, e.g. `2.7.5`.
+    Tag(String)
+}
+
+impl Default for InstallVersion {
+    fn default() -> InstallVersion {
+        InstallVersion::LatestStable
+    }
+}
+
+impl InstallVersion {
+    /// Looks up the matching release.
+    pub fn release(&self, client: &reqwest::Client, repo: &Repo) -> Result<Release, Error> {
+        Ok(match *self {
+            InstallVersion::LatestStable => repo.latest_release(client)?,
+            InstallVersion::LatestPrerelease => repo.releases(client)?.into_iter().find(|release| !release.draft && release.prerelease).ok_or(OtherError::MissingRelease)?,
+            InstallVersion::Tag(ref tag_name) => repo.release_by_tag(client, tag_name)?.ok_or(OtherError::MissingRelease)?
+        })
+    }
+}
+
+/// The official Cockatrice repository, where releases are downloaded from by default.
+pub fn default_repo() -> Repo {
+    Repo::new("Cockatrice", "Cockatrice")
+}
+
+/// Options for `install`.
+#[derive(Debug, Clone)]
+pub struct InstallOptions {
+    /// Install even if the release doesn't list a SHA-256 checksum for the download. If this is not set, such releases are rejected with `OtherError::MissingChecksum`.
+    pub allow_unverified: bool,
+    /// Print progress information to stderr.
+    pub debug: bool,
+    /// The repository to download Cockatrice from. Defaults to `default_repo()`.
+    pub repo: Repo,
+    /// Run the installer without showing any dialogs (NSIS `/S`). Has no effect on Linux, where installation never requires interaction.
+    pub silent: bool,
+    /// Install Cockatrice into this directory instead of the default location.
+    pub target_dir: Option<PathBuf>,
+    /// Which release to install. Defaults to the latest full release.
+    pub version: InstallVersion
+}
+
+impl Default for InstallOptions {
+    fn default() -> InstallOptions {
+        InstallOptions {
+            allow_unverified: false,
+            debug: false,
+            repo: default_repo(),
+            silent: false,
+            target_dir: None,
+            version: InstallVersion::default()
+        }
+    }
+}
+
+/// Downloads and installs Cockatrice.
+///
+/// On Windows, this runs the installer, interactively unless `silent` is set. On Linux, the AppImage is installed to `~/.local/bin` (or `target_dir`) for the current user, with a `.desktop` entry so it shows up in the application menu.
+///
+/// `progress` is called with the number of bytes of the installer or AppImage downloaded so far and the total size, if known.
+#[cfg(any(windows, target_os = "linux"))]
+pub fn install(options: &InstallOptions, progress: impl FnMut(u64, Option<u64>)) -> Result<(), Error> {
+    let debug = options.debug;
     if debug { eprintln!("making reqwest client"); }
     let client = client()?;
     if debug { eprintln!("determining download URL"); }
-    let download_url = {
-        let release_assets = Repo::new("Cockatrice", "Cockatrice").latest_release(&client)?.assets;
-        let (asset,) = release_assets.into_iter()
-            .filter(|asset| asset.name.ends_with(PLATFORM_SUFFIX))
-            .collect_tuple().ok_or(OtherError::MissingAsset)?;
-        asset.browser_download_url
+    let release = options.version.release(&client, &options.repo)?;
+    let manifest = Manifest::fetch(&client, &release)?;
+    let (asset,) = release.assets.into_iter()
+        .filter(|asset| asset.name.ends_with(PLATFORM_SUFFIX))
+        .collect_tuple().ok_or(OtherError::MissingAsset)?;
+    let expected_digest = match manifest.as_ref().and_then(|manifest| manifest.get(&asset.name)) {
+        Some(digest) => Some(digest.to_owned()),
+        None if options.allow_unverified => None,
+        None => { return Err(OtherError::MissingChecksum.into()); }
     };
-    if debug { eprintln!("download URL is {:?}", download_url); }
-    if debug { eprintln!("making tempfile"); }
-    let mut installer_file = tempfile::Builder::new()
-        .prefix("Cockatrice")
-        .suffix(".exe")
-        .tempfile()?;
-    if debug { eprintln!("making download request"); }
-    let mut response = client.get(&download_url).send()?.error_for_status()?;
-    if debug { eprintln!("downloading installer"); }
-    response.copy_to(&mut installer_file)?;
-    let installer_path = installer_file.into_temp_path();
+    if debug { eprintln!("download URL is {:?}, expected SHA-256 is {:?}", asset.browser_download_url, expected_digest); }
+    install_from(&client, &asset, expected_digest, options, progress)
+}
+
+/// Downloads the given asset to the given path, resuming a previous partial download if there is one.
+///
+/// If an expected SHA-256 digest is given and the download doesn't match it, the file is deleted and an error is returned. `None` skips verification, which `install` only allows if `InstallOptions::allow_unverified` is set.
+#[cfg(any(windows, target_os = "linux"))]
+fn download_verified(client: &reqwest::Client, asset: &ReleaseAsset, expected_digest: Option<String>, path: &Path, debug: bool, mut progress: impl FnMut(u64, Option<u64>)) -> Result<(), Error> {
+    util::download(client, &asset.browser_download_url, path, |downloaded, total| {
+        if debug {
+            match total {
+                Some(total) => { eprint!("\rdownloaded {} of {} bytes", downloaded, total); }
+                None => { eprint!("\rdownloaded {} bytes", downloaded); }
+            }
+        }
+        progress(downloaded, total);
+    })?;
+    if debug { eprintln!(); }
+    let actual = checksum::file_digest(path)?;
+    if let Some(expected) = expected_digest {
+        if actual != expected {
+            fs::remove_file(path)?;
+            return Err(OtherError::ChecksumMismatch { expected, actual }.into());
+        }
+    }
+    Ok(())
+}
+
+/// Downloads and installs Cockatrice.
+#[cfg(not(any(windows, target_os = "linux")))]
+pub fn install(_: &InstallOptions, _: impl FnMut(u64, Option<u64>)) -> Result<(), Error> {
+    Err(OtherError::UnsupportedPlatform.into())
+}
+
+#[cfg(windows)]
+fn install_from(client: &reqwest::Client, asset: &ReleaseAsset, expected_digest: Option<String>, options: &InstallOptions, progress: impl FnMut(u64, Option<u64>)) -> Result<(), Error> {
+    let debug = options.debug;
+    let partial_path = env::temp_dir().join(format!("{}.part", asset.name));
+    if debug { eprintln!("downloading installer to {:?}", partial_path); }
+    download_verified(client, asset, expected_digest, &partial_path, debug, progress)?;
+    let installer_path = env::temp_dir().join(&asset.name);
+    fs::rename(&partial_path, &installer_path)?;
     if debug { eprintln!("running installer, path is {:?}", installer_path); }
-    //if !Command::new(&installer_path).status()?.success() {
-    if !Command::new("cmd").arg("/C").arg(&installer_path).status()?.success() { //HACK use `cmd` to get the UAC prompt to display
-        return Err(OtherError::Installer.into());
+    //let mut cmd = Command::new(&installer_path);
+    let mut cmd = Command::new("cmd"); //HACK use `cmd` to get the UAC prompt to display
+    cmd.arg("/C").arg(&installer_path);
+    add_installer_args(&mut cmd, options);
+    let success = cmd.status()?.success();
+    fs::remove_file(&installer_path)?;
+    if !success { return Err(OtherError::Installer.into()); }
+    Ok(())
+}
+
+/// Adds the NSIS command line options for the given install options to a command which runs the installer.
+#[cfg(windows)]
+fn add_installer_args(cmd: &mut Command, options: &InstallOptions) {
+    use std::os::windows::process::CommandExt as _;
+
+    if options.silent { cmd.arg("/S"); }
+    if let Some(ref target_dir) = options.target_dir {
+        // NSIS requires /D to be the last argument, unquoted even if the path contains spaces, so `Command::arg` can't be used since it would add quotes
+        let mut arg = OsString::from("/D=");
+        arg.push(target_dir);
+        cmd.raw_arg(arg);
     }
+}
+
+#[cfg(target_os = "linux")]
+fn install_from(client: &reqwest::Client, asset: &ReleaseAsset, expected_digest: Option<String>, options: &InstallOptions, progress: impl FnMut(u64, Option<u64>)) -> Result<(), Error> {
+    let debug = options.debug;
+    let roots = Roots::from_env();
+    let appimage_dir = match options.target_dir {
+        Some(ref target_dir) => target_dir.clone(),
+        None => roots.appimage_dir().ok_or(OtherError::MissingHomeDir)?
+    };
+    let applications_dir = roots.applications_dir().ok_or(OtherError::MissingHomeDir)?;
+    fs::create_dir_all(&appimage_dir)?;
+    let partial_path = appimage_dir.join(format!(".{}.part", asset.name));
+    if debug { eprintln!("downloading AppImage to {:?}", partial_path); }
+    download_verified(client, asset, expected_digest, &partial_path, debug, progress)?;
+    fs::set_permissions(&partial_path, Permissions::from_mode(0o755))?;
+    let appimage_path = appimage_dir.join("Cockatrice.AppImage");
+    if debug { eprintln!("moving AppImage to {:?}", appimage_path); }
+    fs::rename(&partial_path, &appimage_path)?;
+    let icon_path = match roots.icons_dir() {
+        Some(icons_dir) => match install_icon(&appimage_path, &icons_dir, debug) {
+            Ok(icon_path) => icon_path,
+            Err(e) => {
+                // the launcher still works without an icon, so this isn't fatal
+                if debug { eprintln!("failed to install icon: {}", e); }
+                None
+            }
+        },
+        None => None
+    };
+    fs::create_dir_all(&applications_dir)?;
+    let desktop_path = applications_dir.join("cockatrice.desktop");
+    if debug { eprintln!("writing desktop entry to {:?}", desktop_path); }
+    fs::write(desktop_path, desktop_entry(&appimage_path, icon_path.as_ref().map(PathBuf::as_path)))?;
     Ok(())
 }
+
+/// Copies the AppImage's icon into `icons_dir` and returns its new path, or `None` if the AppImage doesn't include an icon.
+#[cfg(target_os = "linux")]
+fn install_icon(appimage_path: &Path, icons_dir: &Path, debug: bool) -> io::Result<Option<PathBuf>> {
+    let extract_dir = tempfile::tempdir()?;
+    let extract_root = extract_dir.path().join("squashfs-root");
+    let extract = |path: &OsStr| -> io::Result<()> {
+        if debug { eprintln!("extracting {:?} from AppImage", path); }
+        let status = Command::new(appimage_path)
+            .arg("--appimage-extract")
+            .arg(path)
+            .current_dir(extract_dir.path())
+            .stdout(Stdio::null())
+            .status()?;
+        if status.success() {
+            Ok(())
+        } else {
+            Err(io::Error::new(io::ErrorKind::Other, format!("AppImage extraction exited with {}", status)))
+        }
+    };
+    // AppImages include their icon as `.DirIcon`, which is usually a symlink to an icon file in the image root
+    let dir_icon = extract_root.join(".DirIcon");
+    extract(OsStr::new(".DirIcon"))?;
+    let mut extension = OsStr::new("png").to_owned();
+    match fs::read_link(&dir_icon) {
+        Ok(target) => {
+            if target.is_absolute() { return Ok(None); }
+            extract(target.as_os_str())?;
+            if let Some(target_extension) = target.extension() { extension = target_extension.to_owned(); }
+        }
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => { return Ok(None); }
+        Err(ref e) if e.kind() == io::ErrorKind::InvalidInput => {} // not a symlink
+        Err(e) => { return Err(e); }
+    }
+    fs::create_dir_all(icons_dir)?;
+    let icon_path = icons_dir.join("cockatrice").with_extension(extension);
+    if debug { eprintln!("copying icon to {:?}", icon_path); }
+    fs::copy(&dir_icon, &icon_path)?;
+    Ok(Some(icon_path))
+}
+
+/// Returns the contents of the `.desktop` entry that launches the AppImage at the given path.
+#[cfg(target_os = "linux")]
+fn desktop_entry(appimage_path: &Path, icon_path: Option<&Path>) -> String {
+    let mut entry = format!(
+        "[Desktop Entry]\nType=Application\nName=Cockatrice\nComment=Play Magic: The Gathering online\nExec={}\n",
+        desktop_string(&desktop_exec_arg(appimage_path))
+    );
+    if let Some(icon_path) = icon_path {
+        entry.push_str(&format!("Icon={}\n", desktop_string(&icon_path.to_string_lossy())));
+    }
+    entry.push_str("Terminal=false\nCategories=Game;CardGame;\n");
+    entry
+}
+
+/// Quotes a path as a single argument of a desktop entry's `Exec` key, before string escaping is applied.
+#[cfg(target_os = "linux")]
+fn desktop_exec_arg(path: &Path) -> String {
+    let mut arg = String::from("\"");
+    for c in path.to_string_lossy().chars() {
+        match c {
+            '"' | '`' | '$' | '\\' => {
+                arg.push('\\');
+                arg.push(c);
+            }
+            '%' => { arg.push_str("%%"); } // a single % would start a field code
+            c => { arg.push(c); }
+        }
+    }
+    arg.push('"');
+    arg
+}
+
+/// Escapes a value of type `string` in a desktop entry.
+#[cfg(target_os = "linux")]
+fn desktop_string(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' => { escaped.push_str("\\\\"); }
+            '\n' => { escaped.push_str("\\n"); }
+            '\t' => { escaped.push_str("\\t"); }
+            '\r' => { escaped.push_str("\\r"); }
+            c => { escaped.push(c); }
+        }
+    }
+    escaped
+}
+
+/// Whether Cockatrice is installed and up to date, as returned by `check_version`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VersionStatus {
+    /// Cockatrice is not installed.
+    NotInstalled,
+    /// The installed version is the latest release or newer.
+    UpToDate(Version),
+    /// A newer release is available.
+    UpdateAvailable {
+        /// The version that's currently installed.
+        installed: Version,
+        /// The version of the latest release.
+        latest: Version
+    }
+}
+
+/// Returns the installed version of Cockatrice, or `None` if it's not installed, using the default probe for the current platform. `install_dir` is the configured install location, if any.
+pub fn installed_version(install_dir: Option<&Path>) -> io::Result<Option<Version>> {
+    probe::default_probe(install_dir).installed_version()
+}
+
+/// Compares the Cockatrice version reported by the given probe to the latest release in the given repository.
+pub fn check_version(client: &reqwest::Client, repo: &Repo, probe: &dyn Probe) -> Result<VersionStatus, Error> {
+    let installed = match probe.installed_version()? {
+        Some(installed) => installed,
+        None => { return Ok(VersionStatus::NotInstalled); }
+    };
+    let tag_name = repo.latest_release(client)?.tag_name;
+    let latest = probe::parse_version(&tag_name).ok_or_else(|| OtherError::UnparseableTag(tag_name.clone()))?;
+    Ok(if installed < latest {
+        VersionStatus::UpdateAvailable { installed, latest }
+    } else {
+        VersionStatus::UpToDate(installed)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        test_server::{
+            response,
+            serve
+        },
+        util::client
+    };
+    use super::*;
+
+    /// Returns the JSON for a release with the given tag, as listed by the GitHub API.
+    fn release_json(id: u64, tag_name: &str, draft: bool, prerelease: bool) -> serde_json::Value {
+        serde_json::json!({
+            "assets": [],
+            "body": "",
+            "draft": draft,
+            "id": id,
+            "name": tag_name,
+            "prerelease": prerelease,
+            "tag_name": tag_name,
+            "upload_url": ""
+        })
+    }
+
+    #[test]
+    fn latest_prerelease() {
+        let releases = serde_json::to_vec(&vec![
+            release_json(4, "2.8.0-beta2", true, true),
+            release_json(3, "2.7.5", false, false),
+            release_json(2, "2.8.0-beta1", false, true),
+            release_json(1, "2.7.4", false, false)
+        ]).expect("failed to serialize releases");
+        let (base_url, server) = serve(vec![response("200 OK", &["Content-Type: application/json"], releases.len(), &releases)]);
+        let repo = default_repo().with_api_base(base_url);
+        let release = InstallVersion::LatestPrerelease.release(&client().expect("failed to create client"), &repo).expect("failed to find release");
+        assert_eq!(release.tag_name, "2.8.0-beta1");
+        let requests = server.join().expect("test server panicked");
+        assert!(requests[0].path().starts_with("/repos/Cockatrice/Cockatrice/releases?"));
+    }
+
+    #[cfg(any(windows, target_os = "linux"))]
+    #[test]
+    fn missing_checksum() {
+        let mut release = release_json(1, "2.7.5", false, false);
+        release["assets"] = serde_json::json!([{
+            "name": format!("Cockatrice-2.7.5{}", PLATFORM_SUFFIX),
+            "browser_download_url": "http://127.0.0.1:1/not-downloaded"
+        }]);
+        let release = serde_json::to_vec(&release).expect("failed to serialize release");
+        let (base_url, server) = serve(vec![response("200 OK", &["Content-Type: application/json"], release.len(), &release)]);
+        let dir = tempfile::tempdir().expect("failed to create temp dir");
+        let options = InstallOptions {
+            repo: default_repo().with_api_base(base_url),
+            target_dir: Some(dir.path().to_owned()),
+            ..InstallOptions::default()
+        };
+        match install(&options, |_, _| ()) {
+            Err(Error::Other(OtherError::MissingChecksum)) => {}
+            result => panic!("expected missing checksum error, got {:?}", result)
+        }
+        server.join().expect("test server panicked");
+    }
+
+    /// Returns the installer arguments as they appear on the command line, using `echo`, which prints them unchanged.
+    #[cfg(windows)]
+    fn installer_command_line(options: &InstallOptions) -> String {
+        let mut cmd = Command::new("cmd");
+        cmd.arg("/C").arg("echo");
+        add_installer_args(&mut cmd, options);
+        let output = cmd.output().expect("failed to run cmd");
+        String::from_utf8_lossy(&output.stdout).trim_end().to_owned()
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn target_dir_with_spaces() {
+        let target_dir = Some(PathBuf::from(r"C:\Program Files\Cockatrice"));
+        assert_eq!(installer_command_line(&InstallOptions { silent: true, target_dir: target_dir.clone(), ..InstallOptions::default() }), r"/S /D=C:\Program Files\Cockatrice");
+        assert_eq!(installer_command_line(&InstallOptions { target_dir, ..InstallOptions::default() }), r"/D=C:\Program Files\Cockatrice");
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn desktop_exec_escaping() {
+        let exec = |path: &str| desktop_string(&desktop_exec_arg(Path::new(path)));
+        assert_eq!(exec("/home/user/.local/bin/Cockatrice.AppImage"), r#""/home/user/.local/bin/Cockatrice.AppImage""#);
+        assert_eq!(exec("/opt/My Games/Cockatrice.AppImage"), r#""/opt/My Games/Cockatrice.AppImage""#);
+        assert_eq!(exec(r#"/opt/"quoted"/C.AppImage"#), r#""/opt/\\"quoted\\"/C.AppImage""#);
+        assert_eq!(exec("/opt/$HOME/`id`/C.AppImage"), r#""/opt/\\$HOME/\\`id\\`/C.AppImage""#);
+        assert_eq!(exec(r"/opt/back\slash/C.AppImage"), r#""/opt/back\\\\slash/C.AppImage""#);
+        assert_eq!(exec("/opt/100%/C.AppImage"), r#""/opt/100%%/C.AppImage""#);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn desktop_entry_icon() {
+        let entry = desktop_entry(Path::new("/opt/Cockatrice.AppImage"), Some(Path::new("/home/user/.local/share/icons/cockatrice.png")));
+        assert!(entry.contains("\nIcon=/home/user/.local/share/icons/cockatrice.png\n"));
+        assert!(!desktop_entry(Path::new("/opt/Cockatrice.AppImage"), None).contains("Icon="));
+    }
+}
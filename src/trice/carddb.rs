@@ -0,0 +1,633 @@
+//! Reading and writing Cockatrice card databases (`cards.xml` and custom sets, format version 4).
+//!
+//! Writing a card database read by this module and reading it back gives the same `CardDatabase`, but not necessarily the same XML. Not preserved are:
+//!
+//! * comments, processing instructions, and the XML declaration,
+//! * whitespace-only text, and the position of text between the children of unknown elements (it's written before the first child),
+//! * attributes of any elements other than printings (a card's `<set>` entries), `<related>`, `<reverse-related>`, and unknown elements, except the `version` of the root element,
+//! * namespace prefixes,
+//! * the order of the known elements of cards and sets, which are written in a fixed order followed by any unknown elements.
+
+use std::{
+    collections::BTreeMap,
+    fmt,
+    fs::File,
+    io::{
+        self,
+        BufReader,
+        prelude::*
+    },
+    path::Path
+};
+use wrapped_enum::wrapped_enum;
+use xml::{
+    reader::{
+        self,
+        EventReader
+    },
+    writer::{
+        self,
+        EmitterConfig,
+        EventWriter
+    }
+};
+use crate::util;
+
+/// The card database format version this module reads and writes.
+pub const FORMAT_VERSION: &'static str = "4";
+
+/// An error that can occur while reading or writing a card database.
+#[derive(Debug)]
+pub enum OtherError {
+    /// A required child element is missing.
+    MissingElement(&'static str),
+    /// The root element is not `<cockatrice_carddatabase>`.
+    UnexpectedRoot(String),
+    /// The card database uses a format version other than `FORMAT_VERSION`.
+    UnsupportedVersion(Option<String>)
+}
+
+wrapped_enum! {
+    /// An error that can occur while reading or writing a card database.
+    #[derive(Debug)]
+    pub enum Error {
+        #[allow(missing_docs)]
+        Io(io::Error),
+        #[allow(missing_docs)]
+        Other(OtherError),
+        #[allow(missing_docs)]
+        XmlRead(reader::Error),
+        #[allow(missing_docs)]
+        XmlWrite(writer::Error)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::Io(ref e) => e.fmt(f),
+            Error::Other(OtherError::MissingElement(name)) => write!(f, "Card database is missing a <{}> element.", name),
+            Error::Other(OtherError::UnexpectedRoot(ref name)) => write!(f, "Not a Cockatrice card database (root element is <{}>).", name),
+            Error::Other(OtherError::UnsupportedVersion(Some(ref version))) => write!(f, "Unsupported card database version: {}.", version),
+            Error::Other(OtherError::UnsupportedVersion(None)) => write!(f, "Card database version is missing."),
+            Error::XmlRead(ref e) => e.fmt(f),
+            Error::XmlWrite(ref e) => e.fmt(f)
+        }
+    }
+}
+
+/// A generic XML element, used to preserve parts of the card database this module doesn't model.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Element {
+    /// The tag name.
+    pub name: String,
+    /// The attributes, in document order.
+    pub attributes: Vec<(String, String)>,
+    /// The child elements, in document order.
+    pub children: Vec<Element>,
+    /// The concatenated text content of this element, not including that of its children.
+    pub text: String
+}
+
+impl Element {
+    fn attr(&self, name: &str) -> Option<&str> {
+        self.attributes.iter().find(|(k, _)| k == name).map(|(_, v)| &v[..])
+    }
+
+    fn write(&self, w: &mut EventWriter<impl Write>) -> Result<(), writer::Error> {
+        let mut start = writer::XmlEvent::start_element(&self.name[..]);
+        for (k, v) in &self.attributes { start = start.attr(&k[..], v); }
+        w.write(start)?;
+        if !self.text.is_empty() { w.write(writer::XmlEvent::characters(&self.text))?; }
+        for child in &self.children { child.write(w)?; }
+        w.write(writer::XmlEvent::end_element())?;
+        Ok(())
+    }
+}
+
+fn write_leaf(w: &mut EventWriter<impl Write>, name: &str, text: &str) -> Result<(), writer::Error> {
+    w.write(writer::XmlEvent::start_element(name))?;
+    if !text.is_empty() { w.write(writer::XmlEvent::characters(text))?; }
+    w.write(writer::XmlEvent::end_element())?;
+    Ok(())
+}
+
+fn parse_tree(reader: impl Read) -> Result<Element, Error> {
+    let mut stack = Vec::<Element>::default();
+    for event in EventReader::new(reader) {
+        match event? {
+            reader::XmlEvent::StartElement { name, attributes, .. } => {
+                stack.push(Element {
+                    name: name.local_name,
+                    attributes: attributes.into_iter().map(|attr| (attr.name.local_name, attr.value)).collect(),
+                    children: Vec::default(),
+                    text: String::default()
+                });
+            }
+            reader::XmlEvent::Characters(text) | reader::XmlEvent::CData(text) => {
+                if let Some(elt) = stack.last_mut() { elt.text.push_str(&text); }
+            }
+            reader::XmlEvent::EndElement { .. } => {
+                let elt = stack.pop().expect("unbalanced XML events");
+                match stack.last_mut() {
+                    Some(parent) => { parent.children.push(elt); }
+                    None => { return Ok(elt); }
+                }
+            }
+            _ => {}
+        }
+    }
+    Err(OtherError::MissingElement("cockatrice_carddatabase").into())
+}
+
+/// A Cockatrice card database.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct CardDatabase {
+    /// The contents of the `<info>` section, such as `author` and `sourceUrl`, in document order.
+    pub info: Vec<(String, String)>,
+    /// The sets in this card database.
+    pub sets: Vec<Set>,
+    /// The cards in this card database.
+    pub cards: Vec<Card>,
+    /// Any other top-level elements.
+    pub other: Vec<Element>
+}
+
+impl CardDatabase {
+    /// Parses a card database from XML.
+    pub fn from_reader(reader: impl Read) -> Result<CardDatabase, Error> {
+        let root = parse_tree(reader)?;
+        if root.name != "cockatrice_carddatabase" { return Err(OtherError::UnexpectedRoot(root.name).into()); }
+        match root.attr("version") {
+            Some(FORMAT_VERSION) => {}
+            version => { return Err(OtherError::UnsupportedVersion(version.map(str::to_owned)).into()); }
+        }
+        let mut db = CardDatabase::default();
+        for child in root.children {
+            match &child.name[..] {
+                "info" => { db.info.extend(child.children.into_iter().map(|elt| (elt.name, elt.text))); }
+                "sets" => for set in child.children {
+                    db.sets.push(Set::from_element(set)?);
+                },
+                "cards" => for card in child.children {
+                    db.cards.push(Card::from_element(card)?);
+                },
+                _ => { db.other.push(child); }
+            }
+        }
+        Ok(db)
+    }
+
+    /// Reads the card database at the given path.
+    pub fn open(path: impl AsRef<Path>) -> Result<CardDatabase, Error> {
+        CardDatabase::from_reader(BufReader::new(File::open(path)?))
+    }
+
+    /// Writes this card database as XML.
+    pub fn to_writer(&self, writer: impl Write) -> Result<(), Error> {
+        let mut w = EmitterConfig::new().perform_indent(true).create_writer(writer);
+        w.write(writer::XmlEvent::start_element("cockatrice_carddatabase").attr("version", FORMAT_VERSION))?;
+        if !self.info.is_empty() {
+            w.write(writer::XmlEvent::start_element("info"))?;
+            for (k, v) in &self.info { write_leaf(&mut w, k, v)?; }
+            w.write(writer::XmlEvent::end_element())?;
+        }
+        w.write(writer::XmlEvent::start_element("sets"))?;
+        for set in &self.sets { set.write(&mut w)?; }
+        w.write(writer::XmlEvent::end_element())?;
+        w.write(writer::XmlEvent::start_element("cards"))?;
+        for card in &self.cards { card.write(&mut w)?; }
+        w.write(writer::XmlEvent::end_element())?;
+        for elt in &self.other { elt.write(&mut w)?; }
+        w.write(writer::XmlEvent::end_element())?;
+        Ok(())
+    }
+
+    /// Writes this card database to the given path.
+    ///
+    /// The file is replaced atomically, see `util::write_atomic`, so the previous contents are kept if writing fails.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), Error> {
+        let mut buf = Vec::default();
+        self.to_writer(&mut buf)?;
+        util::write_atomic(path, buf)?;
+        Ok(())
+    }
+
+    /// Returns the set with the given set code, if any.
+    pub fn set(&self, code: &str) -> Option<&Set> {
+        self.sets.iter().find(|set| set.name == code)
+    }
+
+    /// Returns the card with the given name, if any.
+    pub fn card(&self, name: &str) -> Option<&Card> {
+        self.cards.iter().find(|card| card.name == name)
+    }
+
+    /// Returns all cards with a printing in the set with the given set code.
+    pub fn cards_in_set<'a>(&'a self, code: &'a str) -> impl Iterator<Item = &'a Card> + 'a {
+        self.cards.iter().filter(move |card| card.printing(code).is_some())
+    }
+
+    /// Compares this card database to a newer version of it, set by set.
+    ///
+    /// A set counts as changed if its `<set>` entry or any card printed in it differs.
+    pub fn diff(&self, new: &CardDatabase) -> Diff {
+        fn by_set(db: &CardDatabase) -> BTreeMap<&str, (Option<&Set>, Vec<&Card>)> {
+            let mut map = BTreeMap::<_, (_, Vec<_>)>::default();
+            for set in &db.sets { map.entry(&set.name[..]).or_insert_with(|| (None, Vec::default())).0 = Some(set); }
+            for card in &db.cards {
+                for printing in &card.sets {
+                    map.entry(&printing.code[..]).or_insert_with(|| (None, Vec::default())).1.push(card);
+                }
+            }
+            for (_, cards) in map.values_mut() { cards.sort_by(|c1, c2| c1.name.cmp(&c2.name)); }
+            map
+        }
+
+        let old_sets = by_set(self);
+        let new_sets = by_set(new);
+        let mut diff = Diff::default();
+        for (code, new_set) in &new_sets {
+            match old_sets.get(code) {
+                None => { diff.added.push(code.to_string()); }
+                Some(old_set) if old_set != new_set => { diff.changed.push(code.to_string()); }
+                Some(_) => {}
+            }
+        }
+        diff.removed = old_sets.keys().filter(|code| !new_sets.contains_key(*code)).map(|code| code.to_string()).collect();
+        diff
+    }
+}
+
+/// The set codes that differ between two card databases, sorted alphabetically. Returned by `CardDatabase::diff`.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Diff {
+    /// Sets which are only in the new card database.
+    pub added: Vec<String>,
+    /// Sets whose set info or cards were modified.
+    pub changed: Vec<String>,
+    /// Sets which are only in the old card database.
+    pub removed: Vec<String>
+}
+
+impl Diff {
+    /// Returns `true` if the card databases contain the same sets and cards.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.changed.is_empty() && self.removed.is_empty()
+    }
+}
+
+impl fmt::Display for Diff {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_empty() { return write!(f, "All sets are up to date."); }
+        let mut first = true;
+        for (label, sets) in &[("Added", &self.added), ("Updated", &self.changed), ("Removed", &self.removed)] {
+            if sets.is_empty() { continue; }
+            if !first { writeln!(f)?; }
+            write!(f, "{} {}: {}", label, if sets.len() == 1 { "set" } else { "sets" }, sets.join(", "))?;
+            first = false;
+        }
+        Ok(())
+    }
+}
+
+/// A `<set>` entry in the `<sets>` section of a card database.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Set {
+    /// The set code, e.g. `DOM`.
+    pub name: String,
+    /// The full name of the set, e.g. `Dominaria`.
+    pub longname: String,
+    /// The set type, e.g. `Expansion` or `Custom`.
+    pub settype: Option<String>,
+    /// The release date in `YYYY-MM-DD` format.
+    pub releasedate: Option<String>,
+    /// Any other child elements.
+    pub other: Vec<Element>
+}
+
+impl Set {
+    fn from_element(elt: Element) -> Result<Set, Error> {
+        let mut name = None;
+        let mut longname = None;
+        let mut set = Set {
+            name: String::default(),
+            longname: String::default(),
+            settype: None,
+            releasedate: None,
+            other: Vec::default()
+        };
+        for child in elt.children {
+            match &child.name[..] {
+                "name" => { name = Some(child.text); }
+                "longname" => { longname = Some(child.text); }
+                "settype" => { set.settype = Some(child.text); }
+                "releasedate" => { set.releasedate = Some(child.text); }
+                _ => { set.other.push(child); }
+            }
+        }
+        set.name = name.ok_or(OtherError::MissingElement("name"))?;
+        set.longname = longname.unwrap_or_default();
+        Ok(set)
+    }
+
+    fn write(&self, w: &mut EventWriter<impl Write>) -> Result<(), writer::Error> {
+        w.write(writer::XmlEvent::start_element("set"))?;
+        write_leaf(w, "name", &self.name)?;
+        write_leaf(w, "longname", &self.longname)?;
+        if let Some(ref settype) = self.settype { write_leaf(w, "settype", settype)?; }
+        if let Some(ref releasedate) = self.releasedate { write_leaf(w, "releasedate", releasedate)?; }
+        for elt in &self.other { elt.write(w)?; }
+        w.write(writer::XmlEvent::end_element())?;
+        Ok(())
+    }
+}
+
+/// A `<card>` entry in the `<cards>` section of a card database.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Card {
+    /// The card name.
+    pub name: String,
+    /// The rules text.
+    pub text: String,
+    /// The card properties such as `manacost`, `cmc`, `type`, `pt`, and `colors`, in document order.
+    pub props: Vec<(String, String)>,
+    /// The sets this card was printed in.
+    pub sets: Vec<Printing>,
+    /// Cards (usually tokens) this card creates or transforms into.
+    pub related: Vec<Related>,
+    /// Cards which create or transform into this card.
+    pub reverse_related: Vec<Related>,
+    /// Whether this card is a token.
+    pub token: bool,
+    /// The row on the table this card is put onto, from 0 (lands) to 3 (stack).
+    pub tablerow: Option<String>,
+    /// Whether this card comes into play tapped.
+    pub cipt: bool,
+    /// Whether this card is displayed upside down (e.g. flip cards).
+    pub upsidedown: bool,
+    /// Any other child elements.
+    pub other: Vec<Element>
+}
+
+impl Card {
+    /// Creates a card with the given name and no other data.
+    pub fn new(name: impl ToString) -> Card {
+        Card {
+            name: name.to_string(),
+            text: String::default(),
+            props: Vec::default(),
+            sets: Vec::default(),
+            related: Vec::default(),
+            reverse_related: Vec::default(),
+            token: false,
+            tablerow: None,
+            cipt: false,
+            upsidedown: false,
+            other: Vec::default()
+        }
+    }
+
+    fn from_element(elt: Element) -> Result<Card, Error> {
+        let mut name = None;
+        let mut card = Card::new("");
+        for child in elt.children {
+            match &child.name[..] {
+                "name" => { name = Some(child.text); }
+                "text" => { card.text = child.text; }
+                "prop" => { card.props.extend(child.children.into_iter().map(|prop| (prop.name, prop.text))); }
+                "set" => { card.sets.push(Printing { code: child.text, attributes: child.attributes }); }
+                "related" => { card.related.push(Related { name: child.text, attributes: child.attributes }); }
+                "reverse-related" => { card.reverse_related.push(Related { name: child.text, attributes: child.attributes }); }
+                "token" => { card.token = child.text.trim() == "1"; }
+                "tablerow" => { card.tablerow = Some(child.text); }
+                "cipt" => { card.cipt = child.text.trim() == "1"; }
+                "upsidedown" => { card.upsidedown = child.text.trim() == "1"; }
+                _ => { card.other.push(child); }
+            }
+        }
+        card.name = name.ok_or(OtherError::MissingElement("name"))?;
+        Ok(card)
+    }
+
+    fn write(&self, w: &mut EventWriter<impl Write>) -> Result<(), writer::Error> {
+        w.write(writer::XmlEvent::start_element("card"))?;
+        write_leaf(w, "name", &self.name)?;
+        write_leaf(w, "text", &self.text)?;
+        if !self.props.is_empty() {
+            w.write(writer::XmlEvent::start_element("prop"))?;
+            for (k, v) in &self.props { write_leaf(w, k, v)?; }
+            w.write(writer::XmlEvent::end_element())?;
+        }
+        for printing in &self.sets { printing.write(w)?; }
+        for related in &self.related { related.write(w, "related")?; }
+        for related in &self.reverse_related { related.write(w, "reverse-related")?; }
+        if self.token { write_leaf(w, "token", "1")?; }
+        if let Some(ref tablerow) = self.tablerow { write_leaf(w, "tablerow", tablerow)?; }
+        if self.cipt { write_leaf(w, "cipt", "1")?; }
+        if self.upsidedown { write_leaf(w, "upsidedown", "1")?; }
+        for elt in &self.other { elt.write(w)?; }
+        w.write(writer::XmlEvent::end_element())?;
+        Ok(())
+    }
+
+    /// Returns the value of the given property, if present.
+    pub fn prop(&self, key: &str) -> Option<&str> {
+        self.props.iter().find(|(k, _)| k == key).map(|(_, v)| &v[..])
+    }
+
+    /// Sets the given property, replacing its value in place if it already exists.
+    pub fn set_prop(&mut self, key: impl ToString, value: impl ToString) {
+        let key = key.to_string();
+        let value = value.to_string();
+        match self.props.iter_mut().find(|(k, _)| *k == key) {
+            Some((_, v)) => { *v = value; }
+            None => { self.props.push((key, value)); }
+        }
+    }
+
+    /// The mana cost, e.g. `{2}{G}`.
+    pub fn manacost(&self) -> Option<&str> { self.prop("manacost") }
+
+    /// The converted mana cost.
+    pub fn cmc(&self) -> Option<&str> { self.prop("cmc") }
+
+    /// The full type line, e.g. `Creature — Elf Druid`.
+    pub fn type_line(&self) -> Option<&str> { self.prop("type") }
+
+    /// Power and toughness, e.g. `1/1`.
+    pub fn pt(&self) -> Option<&str> { self.prop("pt") }
+
+    /// The colors as a string of color letters, e.g. `WU`.
+    pub fn colors(&self) -> Option<&str> { self.prop("colors") }
+
+    /// Returns this card's printing in the set with the given set code, if any.
+    pub fn printing(&self, code: &str) -> Option<&Printing> {
+        self.sets.iter().find(|printing| printing.code == code)
+    }
+}
+
+/// A `<set>` entry of a card, representing a printing of that card.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Printing {
+    /// The set code.
+    pub code: String,
+    /// The attributes such as `rarity`, `num`, `uuid`, and `picurl`, in document order.
+    pub attributes: Vec<(String, String)>
+}
+
+impl Printing {
+    fn write(&self, w: &mut EventWriter<impl Write>) -> Result<(), writer::Error> {
+        let mut start = writer::XmlEvent::start_element("set");
+        for (k, v) in &self.attributes { start = start.attr(&k[..], v); }
+        w.write(start)?;
+        w.write(writer::XmlEvent::characters(&self.code))?;
+        w.write(writer::XmlEvent::end_element())?;
+        Ok(())
+    }
+
+    /// Returns the value of the given attribute, if present.
+    pub fn attr(&self, name: &str) -> Option<&str> {
+        self.attributes.iter().find(|(k, _)| k == name).map(|(_, v)| &v[..])
+    }
+
+    /// The picture URL for this printing, overriding the URL templates configured in Cockatrice.
+    pub fn picurl(&self) -> Option<&str> { self.attr("picurl") }
+
+    /// The collector number.
+    pub fn num(&self) -> Option<&str> { self.attr("num") }
+
+    /// The rarity, e.g. `common`.
+    pub fn rarity(&self) -> Option<&str> { self.attr("rarity") }
+}
+
+/// A `<related>` or `<reverse-related>` entry of a card.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Related {
+    /// The name of the related card.
+    pub name: String,
+    /// The attributes such as `count`, `attach`, `exclude`, and `persistent`, in document order.
+    pub attributes: Vec<(String, String)>
+}
+
+impl Related {
+    /// Creates a relation to the card with the given name, without attributes.
+    pub fn new(name: impl ToString) -> Related {
+        Related {
+            name: name.to_string(),
+            attributes: Vec::default()
+        }
+    }
+
+    fn write(&self, w: &mut EventWriter<impl Write>, tag: &str) -> Result<(), writer::Error> {
+        let mut start = writer::XmlEvent::start_element(tag);
+        for (k, v) in &self.attributes { start = start.attr(&k[..], v); }
+        w.write(start)?;
+        w.write(writer::XmlEvent::characters(&self.name))?;
+        w.write(writer::XmlEvent::end_element())?;
+        Ok(())
+    }
+
+    /// The number of copies created, e.g. `2` or `x`.
+    pub fn count(&self) -> Option<&str> {
+        self.attributes.iter().find(|(k, _)| k == "count").map(|(_, v)| &v[..])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A card database using everything this module models, as well as unknown elements and a comment.
+    static FIXTURE: &'static str = include_str!("../../tests/fixtures/carddb-v4.xml");
+
+    fn fixture() -> CardDatabase {
+        CardDatabase::from_reader(FIXTURE.as_bytes()).expect("failed to parse fixture")
+    }
+
+    fn round_trip(db: &CardDatabase) -> CardDatabase {
+        let mut buf = Vec::default();
+        db.to_writer(&mut buf).expect("failed to write card database");
+        CardDatabase::from_reader(&buf[..]).expect("failed to parse written card database")
+    }
+
+    #[test]
+    fn read_info_and_sets() {
+        let db = fixture();
+        assert_eq!(db.info[0], (format!("author"), format!("Lore Seeker")));
+        assert_eq!(db.info.len(), 4);
+        let set = db.set("VLN").expect("missing set");
+        assert_eq!(set.longname, "Vorthos Legends");
+        assert_eq!(set.settype.as_ref().map(String::as_str), Some("Custom"));
+        assert_eq!(set.releasedate.as_ref().map(String::as_str), Some("2019-08-30"));
+        let token_set = db.set("TVLN").expect("missing token set");
+        assert_eq!(token_set.other.len(), 1);
+        assert_eq!(token_set.other[0].name, "tokenset");
+        assert_eq!(db.other.len(), 1);
+        assert_eq!(db.other[0].attributes, vec![(format!("source"), format!("lore-seeker"))]);
+    }
+
+    #[test]
+    fn read_cards() {
+        let db = fixture();
+        let card = db.card("Grove Warden").expect("missing card");
+        assert!(card.text.ends_with("\nElf Warriors you control get +1/+0."));
+        assert_eq!(card.manacost(), Some("{2}{G}{G}"));
+        assert_eq!(card.cmc(), Some("4"));
+        assert_eq!(card.type_line(), Some("Creature — Elf Druid"));
+        assert_eq!(card.pt(), Some("3/3"));
+        assert_eq!(card.props[0], (format!("layout"), format!("normal")));
+        let printing = card.printing("VLN").expect("missing printing");
+        assert_eq!(printing.rarity(), Some("rare"));
+        assert_eq!(printing.num(), Some("142"));
+        assert_eq!(printing.picurl(), Some("https://lore-seeker.cards/cards/vln/142.png"));
+        assert_eq!(printing.attributes.len(), 4);
+        assert_eq!(card.related, vec![Related { name: format!("Elf Warrior"), attributes: vec![(format!("count"), format!("2"))] }]);
+        assert_eq!(card.related[0].count(), Some("2"));
+        assert!(!card.token);
+        assert_eq!(card.tablerow.as_ref().map(String::as_str), Some("2"));
+        assert!(db.card("Shrine of the Lost").expect("missing card").cipt);
+        let token = db.card("Elf Warrior").expect("missing token");
+        assert!(token.token);
+        assert!(token.upsidedown);
+        assert!(token.text.is_empty());
+        assert_eq!(token.reverse_related[0].name, "Grove Warden");
+        assert_eq!(token.reverse_related[0].attributes.len(), 2);
+        assert_eq!(token.other[0].name, "artist");
+        assert_eq!(token.other[0].text, "Unknown");
+        assert_eq!(db.cards_in_set("VLN").count(), 2);
+    }
+
+    #[test]
+    fn round_trip_is_lossless() {
+        let db = fixture();
+        assert_eq!(round_trip(&db), db);
+        assert_eq!(round_trip(&CardDatabase::default()), CardDatabase::default());
+    }
+
+    #[test]
+    fn reject_other_versions() {
+        let v3 = FIXTURE.replace("version=\"4\"", "version=\"3\"");
+        match CardDatabase::from_reader(v3.as_bytes()) {
+            Err(Error::Other(OtherError::UnsupportedVersion(Some(ref version)))) if version == "3" => {}
+            result => { panic!("unexpected result: {:?}", result); }
+        }
+        match CardDatabase::from_reader(&b"<cockatrice_deck version=\"1\"/>"[..]) {
+            Err(Error::Other(OtherError::UnexpectedRoot(ref name))) if name == "cockatrice_deck" => {}
+            result => { panic!("unexpected result: {:?}", result); }
+        }
+    }
+
+    #[test]
+    fn diff() {
+        let old = fixture();
+        let mut new = old.clone();
+        new.cards.retain(|card| card.name != "Shrine of the Lost");
+        new.sets.push(Set { name: format!("NEW"), longname: format!("New Set"), settype: None, releasedate: None, other: Vec::default() });
+        let diff = old.diff(&new);
+        assert_eq!(diff.added, vec![format!("NEW")]);
+        assert_eq!(diff.changed, vec![format!("VLN")]);
+        assert!(diff.removed.is_empty());
+        assert!(old.diff(&old).is_empty());
+    }
+}
@@ -0,0 +1,215 @@
+//! Locating Cockatrice's installation and data directories.
+
+use std::{
+    env,
+    path::{
+        Path,
+        PathBuf
+    }
+};
+
+/// The files and folders in a Cockatrice data directory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Paths {
+    /// The data directory itself.
+    pub data_dir: PathBuf
+}
+
+impl Paths {
+    /// Uses the given directory as the Cockatrice data directory.
+    pub fn new(data_dir: impl Into<PathBuf>) -> Paths {
+        Paths { data_dir: data_dir.into() }
+    }
+
+    /// Returns the paths for the data directory Cockatrice uses for the current user, if it exists.
+    pub fn find() -> Option<Paths> {
+        Roots::from_env().find()
+    }
+
+    /// Returns `true` if the data directory exists.
+    pub fn exists(&self) -> bool {
+        self.data_dir.is_dir()
+    }
+
+    /// The main card database, `cards.xml`.
+    pub fn cards_xml(&self) -> PathBuf {
+        self.data_dir.join("cards.xml")
+    }
+
+    /// The token database, `tokens.xml`.
+    pub fn tokens_xml(&self) -> PathBuf {
+        self.data_dir.join("tokens.xml")
+    }
+
+    /// The folder from which Cockatrice loads custom sets in addition to `cards.xml`.
+    pub fn customsets_dir(&self) -> PathBuf {
+        self.data_dir.join("customsets")
+    }
+
+    /// The folder containing Cockatrice's settings files.
+    pub fn settings_dir(&self) -> PathBuf {
+        self.data_dir.join("settings")
+    }
+
+    /// The main settings file, `settings/global.ini`.
+    pub fn global_ini(&self) -> PathBuf {
+        self.settings_dir().join("global.ini")
+    }
+
+    /// The picture download settings file, `settings/downloads.ini`.
+    pub fn downloads_ini(&self) -> PathBuf {
+        self.settings_dir().join("downloads.ini")
+    }
+}
+
+/// The directories from which the Cockatrice paths are derived.
+///
+/// `Roots::from_env` reads these from the environment, but they can also be set explicitly, e.g. to a temporary directory.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Roots {
+    /// The user's home directory.
+    pub home: Option<PathBuf>,
+    /// `%LOCALAPPDATA%` on Windows.
+    pub local_app_data: Option<PathBuf>,
+    /// `$XDG_DATA_HOME` on Linux, if set.
+    pub xdg_data_home: Option<PathBuf>,
+    /// The directory containing the Cockatrice executable, used to detect portable mode.
+    pub install_dir: Option<PathBuf>
+}
+
+impl Roots {
+    /// Reads the roots from environment variables.
+    pub fn from_env() -> Roots {
+        Roots {
+            home: env::var_os("HOME").or_else(|| env::var_os("USERPROFILE")).map(PathBuf::from),
+            local_app_data: env::var_os("LOCALAPPDATA").map(PathBuf::from),
+            xdg_data_home: env::var_os("XDG_DATA_HOME").filter(|dir| !dir.is_empty()).map(PathBuf::from),
+            install_dir: default_install_dir()
+        }
+    }
+
+    /// Uses the given directory as the root for everything, with the same layout that would be used in a home directory on the current platform.
+    ///
+    /// Useful for testing against a temporary directory.
+    pub fn with_base(base: impl AsRef<Path>) -> Roots {
+        let base = base.as_ref();
+        Roots {
+            home: Some(base.to_owned()),
+            local_app_data: Some(base.join("AppData").join("Local")),
+            xdg_data_home: None,
+            install_dir: None
+        }
+    }
+
+    /// Returns the data directory Cockatrice would use if installed in portable mode, if a portable installation is present.
+    pub fn portable_data_dir(&self) -> Option<PathBuf> {
+        let install_dir = self.install_dir.as_ref()?;
+        if install_dir.join("portable.dat").exists() {
+            Some(install_dir.join("data"))
+        } else {
+            None
+        }
+    }
+
+    /// Returns the data directory a regular (non-portable) Cockatrice installation would use on the current platform, whether or not it exists.
+    pub fn user_data_dir(&self) -> Option<PathBuf> {
+        if cfg!(windows) {
+            Some(self.local_app_data.as_ref()?.join("Cockatrice").join("Cockatrice"))
+        } else if cfg!(target_os = "macos") {
+            Some(self.home.as_ref()?.join("Library").join("Application Support").join("Cockatrice").join("Cockatrice"))
+        } else {
+            Some(self.data_home()?.join("Cockatrice").join("Cockatrice"))
+        }
+    }
+
+    /// Returns the `XDG_DATA_HOME` directory, falling back to `~/.local/share`.
+    fn data_home(&self) -> Option<PathBuf> {
+        match self.xdg_data_home {
+            Some(ref xdg_data_home) => Some(xdg_data_home.clone()),
+            None => Some(self.home.as_ref()?.join(".local").join("share"))
+        }
+    }
+
+    /// The directory where `trice::install` puts the Cockatrice AppImage on Linux.
+    pub fn appimage_dir(&self) -> Option<PathBuf> {
+        Some(self.home.as_ref()?.join(".local").join("bin"))
+    }
+
+    /// The directory for per-user `.desktop` entries on Linux.
+    pub fn applications_dir(&self) -> Option<PathBuf> {
+        Some(self.data_home()?.join("applications"))
+    }
+
+    /// The directory where `trice::install` puts the Cockatrice icon on Linux.
+    pub fn icons_dir(&self) -> Option<PathBuf> {
+        Some(self.data_home()?.join("icons"))
+    }
+
+    /// Returns the paths Cockatrice uses, preferring a portable installation, whether or not the data directory exists.
+    pub fn paths(&self) -> Option<Paths> {
+        self.portable_data_dir().or_else(|| self.user_data_dir()).map(Paths::new)
+    }
+
+    /// Returns the paths Cockatrice uses, if the data directory exists.
+    pub fn find(&self) -> Option<Paths> {
+        self.paths().filter(Paths::exists)
+    }
+}
+
+/// Returns the directory where the Cockatrice installer puts the executable by default, if there is one on the current platform.
+fn default_install_dir() -> Option<PathBuf> {
+    if cfg!(windows) {
+        env::var_os("ProgramFiles").map(|program_files| Path::new(&program_files).join("Cockatrice"))
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use super::*;
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn xdg_data_home() {
+        let base = tempfile::tempdir().expect("failed to create temp dir");
+        let mut roots = Roots::with_base(base.path());
+        assert_eq!(roots.user_data_dir(), Some(base.path().join(".local").join("share").join("Cockatrice").join("Cockatrice")));
+        assert_eq!(roots.applications_dir(), Some(base.path().join(".local").join("share").join("applications")));
+        roots.xdg_data_home = Some(base.path().join("xdg"));
+        assert_eq!(roots.user_data_dir(), Some(base.path().join("xdg").join("Cockatrice").join("Cockatrice")));
+        assert_eq!(roots.applications_dir(), Some(base.path().join("xdg").join("applications")));
+        assert_eq!(roots.appimage_dir(), Some(base.path().join(".local").join("bin")));
+    }
+
+    #[test]
+    fn portable() {
+        let base = tempfile::tempdir().expect("failed to create temp dir");
+        let install_dir = base.path().join("Cockatrice");
+        fs::create_dir(&install_dir).expect("failed to create install dir");
+        let roots = Roots { install_dir: Some(install_dir.clone()), ..Roots::with_base(base.path()) };
+        assert_eq!(roots.portable_data_dir(), None);
+        assert_eq!(roots.paths(), roots.user_data_dir().map(Paths::new));
+        fs::write(install_dir.join("portable.dat"), "").expect("failed to create portable.dat");
+        assert_eq!(roots.portable_data_dir(), Some(install_dir.join("data")));
+        assert_eq!(roots.paths(), Some(Paths::new(install_dir.join("data"))));
+    }
+
+    #[test]
+    fn find() {
+        let base = tempfile::tempdir().expect("failed to create temp dir");
+        let roots = Roots::with_base(base.path());
+        assert_eq!(roots.find(), None);
+        let data_dir = roots.user_data_dir().expect("no data dir on this platform");
+        fs::create_dir_all(&data_dir).expect("failed to create data dir");
+        assert_eq!(roots.find(), Some(Paths::new(&data_dir)));
+        assert_eq!(roots.find().map(|paths| paths.tokens_xml()), Some(data_dir.join("tokens.xml")));
+    }
+
+    #[test]
+    fn missing_home() {
+        assert_eq!(Roots::default().paths(), None);
+        assert_eq!(Roots::default().find(), None);
+    }
+}
@@ -0,0 +1,120 @@
+//! Registering Lore Seeker as a source for card pictures in Cockatrice.
+
+use std::io;
+use crate::lore_seeker::Server;
+use super::{
+    paths::Paths,
+    settings::DownloadSettings
+};
+
+/// The path of Lore Seeker card pictures on the server, using Cockatrice's placeholder syntax.
+static PIC_PATH: &'static str = "/cards_hq/!set!/!setnumber!.png";
+
+/// The URL templates Cockatrice uses if none are configured. Kept when registering Lore Seeker so Cockatrice can still download pictures for regular cards.
+static COCKATRICE_DEFAULT_URLS: [&'static str; 3] = [
+    "https://api.scryfall.com/cards/!set:uuid!?format=image&face=!prop:side!",
+    "https://api.scryfall.com/cards/multiverse/!set:muid!?format=image",
+    "https://api.scryfall.com/cards/named?fuzzy=!name!&format=image"
+];
+
+/// Returns the URL template for card pictures from the given Lore Seeker server.
+pub fn pic_url(server: &Server) -> String {
+    server.url(PIC_PATH)
+}
+
+/// Checks whether the given URL template is for pictures from any Lore Seeker server, so that switching servers doesn't leave the old one registered.
+fn is_lore_seeker_url(url: &str) -> bool {
+    url.ends_with(PIC_PATH)
+}
+
+/// Adds the picture URL template of the given Lore Seeker server to Cockatrice's picture download sources, replacing any other Lore Seeker server.
+///
+/// Cockatrice can't authenticate picture downloads, so this only works for servers which serve pictures without an auth token.
+///
+/// `priority` is the position in the list, with 0 meaning Lore Seeker is tried first. If it's larger than the number of other sources, Lore Seeker is tried last. If the template is already registered, it is moved to the requested position.
+///
+/// Returns `false` if the settings were already as requested.
+pub fn register(paths: &Paths, server: &Server, priority: usize) -> io::Result<bool> {
+    let mut settings = DownloadSettings::open(paths)?;
+    if !register_in(&mut settings, server, priority) { return Ok(false); }
+    settings.save()?;
+    Ok(true)
+}
+
+/// Like `register`, but only modifies the given settings without saving them.
+pub fn register_in(settings: &mut DownloadSettings, server: &Server, priority: usize) -> bool {
+    let old_urls = settings.urls().unwrap_or_else(|| COCKATRICE_DEFAULT_URLS.iter().map(|url| url.to_string()).collect());
+    let mut new_urls = old_urls.iter().filter(|url| !is_lore_seeker_url(url)).cloned().collect::<Vec<_>>();
+    new_urls.insert(priority.min(new_urls.len()), pic_url(server));
+    if settings.urls().map_or(false, |urls| urls == new_urls) { return false; }
+    settings.set_urls(&new_urls);
+    true
+}
+
+/// Removes the picture URL templates of all Lore Seeker servers from Cockatrice's picture download sources.
+///
+/// Returns `false` if it wasn't registered.
+pub fn unregister(paths: &Paths) -> io::Result<bool> {
+    let mut settings = DownloadSettings::open(paths)?;
+    if !unregister_in(&mut settings) { return Ok(false); }
+    settings.save()?;
+    Ok(true)
+}
+
+/// Like `unregister`, but only modifies the given settings without saving them.
+pub fn unregister_in(settings: &mut DownloadSettings) -> bool {
+    let urls = match settings.urls() {
+        Some(urls) => urls,
+        None => { return false; }
+    };
+    if !urls.iter().any(|url| is_lore_seeker_url(url)) { return false; }
+    settings.set_urls(&urls.into_iter().filter(|url| !is_lore_seeker_url(url)).collect::<Vec<_>>());
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::settings::Ini;
+    use super::*;
+
+    fn parse(text: &str) -> DownloadSettings {
+        DownloadSettings::new("downloads.ini", Ini::parse(text))
+    }
+
+    fn server(base_url: &str) -> Server {
+        Server { base_url: base_url.to_owned(), auth_token: None }
+    }
+
+    static USER_URLS: &'static str = "[downloads]\nurls=https://example.com/!name!.jpg, https://example.org/!set!/!name!.png\n";
+
+    #[test]
+    fn register_keeps_user_urls() {
+        let mut settings = parse(USER_URLS);
+        assert!(register_in(&mut settings, &server("https://lore-seeker.cards"), 1));
+        assert_eq!(settings.urls(), Some(vec![
+            "https://example.com/!name!.jpg".to_owned(),
+            "https://lore-seeker.cards/cards_hq/!set!/!setnumber!.png".to_owned(),
+            "https://example.org/!set!/!name!.png".to_owned()
+        ]));
+        assert!(!register_in(&mut settings, &server("https://lore-seeker.cards/"), 1));
+        assert!(register_in(&mut settings, &server("https://lore-seeker.example"), 0));
+        assert_eq!(settings.urls(), Some(vec![
+            "https://lore-seeker.example/cards_hq/!set!/!setnumber!.png".to_owned(),
+            "https://example.com/!name!.jpg".to_owned(),
+            "https://example.org/!set!/!name!.png".to_owned()
+        ]));
+        assert!(unregister_in(&mut settings));
+        assert_eq!(settings.urls(), parse(USER_URLS).urls());
+        assert!(!unregister_in(&mut settings));
+    }
+
+    #[test]
+    fn register_keeps_defaults() {
+        let mut settings = parse("");
+        assert!(!unregister_in(&mut settings));
+        assert!(register_in(&mut settings, &server("https://lore-seeker.cards"), 0));
+        let urls = settings.urls().expect("no URLs set");
+        assert_eq!(urls[0], "https://lore-seeker.cards/cards_hq/!set!/!setnumber!.png");
+        assert_eq!(&urls[1..], &COCKATRICE_DEFAULT_URLS.iter().map(|url| url.to_string()).collect::<Vec<_>>()[..]);
+    }
+}
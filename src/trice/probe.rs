@@ -0,0 +1,197 @@
+//! Detecting which version of Cockatrice is installed.
+
+use std::{
+    io,
+    path::{
+        Path,
+        PathBuf
+    },
+    process::{
+        Command,
+        Stdio
+    },
+    thread,
+    time::{
+        Duration,
+        Instant
+    }
+};
+use semver::Version;
+#[cfg(target_os = "linux")]
+use super::paths::Roots;
+
+/// A way of determining the installed Cockatrice version.
+pub trait Probe {
+    /// Returns the installed version, or `None` if Cockatrice is not installed.
+    fn installed_version(&self) -> io::Result<Option<Version>>;
+}
+
+/// A probe which can't be used, e.g. because the install location can't be determined, reports Cockatrice as not installed.
+impl<P: Probe> Probe for Option<P> {
+    fn installed_version(&self) -> io::Result<Option<Version>> {
+        match *self {
+            Some(ref probe) => probe.installed_version(),
+            None => Ok(None)
+        }
+    }
+}
+
+/// Reads the `DisplayVersion` of Cockatrice from the uninstall information the Windows installer writes to the registry.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct RegistryProbe;
+
+/// The registry keys where the Cockatrice installer may register its uninstaller: 64-bit and 32-bit installs for all users, then per-user installs.
+static UNINSTALL_KEYS: [&'static str; 4] = [
+    r"HKLM\SOFTWARE\Microsoft\Windows\CurrentVersion\Uninstall\Cockatrice",
+    r"HKLM\SOFTWARE\WOW6432Node\Microsoft\Windows\CurrentVersion\Uninstall\Cockatrice",
+    r"HKCU\SOFTWARE\Microsoft\Windows\CurrentVersion\Uninstall\Cockatrice",
+    r"HKCU\SOFTWARE\WOW6432Node\Microsoft\Windows\CurrentVersion\Uninstall\Cockatrice"
+];
+
+impl Probe for RegistryProbe {
+    fn installed_version(&self) -> io::Result<Option<Version>> {
+        for key in &UNINSTALL_KEYS {
+            let output = Command::new("reg").arg("query").arg(key).arg("/v").arg("DisplayVersion").output()?;
+            if !output.status.success() { continue; } // key doesn't exist
+            if let Some(version) = parse_reg_query(&String::from_utf8_lossy(&output.stdout)) { return Ok(Some(version)); }
+        }
+        Ok(None)
+    }
+}
+
+/// How long `BinaryProbe::new` waits for Cockatrice to print its version, in case it ignores `--version` and opens its window instead.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Runs the Cockatrice executable with `--version` and reads the version from its output.
+#[derive(Debug, Clone)]
+pub struct BinaryProbe {
+    /// The path to the Cockatrice executable or AppImage.
+    pub path: PathBuf,
+    /// How long to wait for the executable to exit. If it's still running after this, it's killed and an error of kind `TimedOut` is returned.
+    pub timeout: Duration
+}
+
+impl BinaryProbe {
+    /// Creates a probe for the executable at the given path, with the default timeout.
+    pub fn new(path: impl Into<PathBuf>) -> BinaryProbe {
+        BinaryProbe {
+            path: path.into(),
+            timeout: DEFAULT_TIMEOUT
+        }
+    }
+}
+
+impl Probe for BinaryProbe {
+    fn installed_version(&self) -> io::Result<Option<Version>> {
+        if !self.path.exists() { return Ok(None); }
+        let mut child = Command::new(&self.path)
+            .arg("--version")
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+        let start = Instant::now();
+        while child.try_wait()?.is_none() {
+            if start.elapsed() >= self.timeout {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(io::Error::new(io::ErrorKind::TimedOut, "Cockatrice did not report its version"));
+            }
+            thread::sleep(Duration::from_millis(50));
+        }
+        let output = child.wait_with_output()?;
+        Ok(
+            parse_version(&String::from_utf8_lossy(&output.stdout))
+                .or_else(|| parse_version(&String::from_utf8_lossy(&output.stderr)))
+        )
+    }
+}
+
+/// Returns the probe for the current platform, looking for Cockatrice in `install_dir` if given, or the default installation location otherwise.
+///
+/// On Windows, `install_dir` is ignored since the installer registers itself wherever it's installed. On Linux, if no `install_dir` is given and the home directory can't be determined, the returned probe reports Cockatrice as not installed.
+pub fn default_probe(install_dir: Option<&Path>) -> Box<dyn Probe> {
+    #[cfg(windows)] { let _ = install_dir; Box::new(RegistryProbe) }
+    #[cfg(target_os = "linux")] {
+        let dir = install_dir.map(Path::to_owned).or_else(|| Roots::from_env().appimage_dir());
+        Box::new(dir.map(|dir| BinaryProbe::new(dir.join("Cockatrice.AppImage"))))
+    }
+    #[cfg(not(any(windows, target_os = "linux")))] { Box::new(BinaryProbe::new(install_dir.map_or_else(|| PathBuf::from("cockatrice"), |dir| dir.join("cockatrice")))) }
+}
+
+/// Reads the `DisplayVersion` value from the output of `reg query`.
+pub fn parse_reg_query(output: &str) -> Option<Version> {
+    output.lines()
+        .find(|line| line.trim_start().starts_with("DisplayVersion"))
+        .and_then(parse_version)
+}
+
+/// Finds the first version number in the given text, such as a Cockatrice tag name like `2019-08-31-Release-2.7.2` or the output of `cockatrice --version`.
+///
+/// Version numbers with only two components are treated as having a patch version of 0.
+pub fn parse_version(text: &str) -> Option<Version> {
+    text.split(|c: char| !c.is_ascii_digit() && c != '.')
+        .map(|run| run.trim_matches('.'))
+        .filter(|run| run.contains('.'))
+        .filter_map(|run| run.split('.').map(|component| component.parse::<u64>()).collect::<Result<Vec<_>, _>>().ok())
+        .map(|components| Version::new(components[0], components[1], components.get(2).cloned().unwrap_or(0)))
+        .next()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_versions() {
+        assert_eq!(parse_version("2019-08-31-Release-2.7.2"), Some(Version::new(2, 7, 2)));
+        assert_eq!(parse_version("Cockatrice 2.7"), Some(Version::new(2, 7, 0)));
+        assert_eq!(parse_version("no version here"), None);
+        assert_eq!(parse_reg_query("\r\nHKEY_LOCAL_MACHINE\\SOFTWARE\\Cockatrice\r\n    DisplayVersion    REG_SZ    2.7.5\r\n"), Some(Version::new(2, 7, 5)));
+    }
+
+    #[test]
+    fn missing_probe() {
+        assert_eq!(None::<BinaryProbe>.installed_version().expect("probe failed"), None);
+        assert_eq!(BinaryProbe::new("/nonexistent/Cockatrice.AppImage").installed_version().expect("probe failed"), None);
+    }
+
+    #[cfg(unix)]
+    fn script(dir: &tempfile::TempDir, name: &str, body: &str) -> PathBuf {
+        use std::{
+            fs,
+            os::unix::fs::PermissionsExt as _
+        };
+
+        let path = dir.path().join(name);
+        fs::write(&path, format!("#!/bin/sh\n{}\n", body)).expect("failed to write script");
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).expect("failed to make script executable");
+        path
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn binary_probe() {
+        let dir = tempfile::tempdir().expect("failed to create temp dir");
+        let probe = BinaryProbe::new(script(&dir, "cockatrice", "echo 'Cockatrice 2.7.5' >&2"));
+        assert_eq!(probe.installed_version().expect("probe failed"), Some(Version::new(2, 7, 5)));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn binary_probe_timeout() {
+        let dir = tempfile::tempdir().expect("failed to create temp dir");
+        let probe = BinaryProbe { timeout: Duration::from_millis(200), ..BinaryProbe::new(script(&dir, "cockatrice", "exec sleep 60")) };
+        let start = Instant::now();
+        assert_eq!(probe.installed_version().expect_err("probe should time out").kind(), io::ErrorKind::TimedOut);
+        assert!(start.elapsed() < Duration::from_secs(10));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn configured_install_dir() {
+        let dir = tempfile::tempdir().expect("failed to create temp dir");
+        script(&dir, "Cockatrice.AppImage", "echo 'Cockatrice 2.7.5'");
+        assert_eq!(default_probe(Some(dir.path())).installed_version().expect("probe failed"), Some(Version::new(2, 7, 5)));
+    }
+}
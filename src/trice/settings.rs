@@ -0,0 +1,429 @@
+//! Reading and editing Cockatrice's settings files, such as `settings/global.ini`.
+//!
+//! Cockatrice stores its settings using Qt's INI format. `Ini` only rewrites the lines for keys that are modified, so comments, ordering, and settings this module doesn't know about are preserved.
+
+use std::{
+    fmt,
+    fs,
+    io,
+    path::{
+        Path,
+        PathBuf
+    }
+};
+use crate::util;
+use super::paths::Paths;
+
+#[derive(Debug, Clone)]
+enum Line {
+    Section {
+        name: String,
+        raw: String
+    },
+    Entry {
+        key: String,
+        raw: String
+    },
+    Other(String)
+}
+
+/// The contents of an INI file.
+#[derive(Debug, Default, Clone)]
+pub struct Ini {
+    lines: Vec<Line>,
+    crlf: bool
+}
+
+impl Ini {
+    /// Parses an INI file.
+    pub fn parse(text: &str) -> Ini {
+        Ini {
+            lines: text.lines().map(|line| {
+                let trimmed = line.trim();
+                if trimmed.starts_with('[') && trimmed.ends_with(']') {
+                    Line::Section {
+                        name: trimmed[1..trimmed.len() - 1].trim().to_owned(),
+                        raw: line.to_owned()
+                    }
+                } else if trimmed.starts_with(';') || trimmed.starts_with('#') {
+                    Line::Other(line.to_owned())
+                } else if let Some(eq_idx) = line.find('=') {
+                    Line::Entry {
+                        key: line[..eq_idx].trim().to_owned(),
+                        raw: line.to_owned()
+                    }
+                } else {
+                    Line::Other(line.to_owned())
+                }
+            }).collect(),
+            crlf: text.contains("\r\n")
+        }
+    }
+
+    /// Reads the INI file at the given path. A missing file is treated as empty.
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Ini> {
+        match fs::read_to_string(path) {
+            Ok(text) => Ok(Ini::parse(&text)),
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(Ini::default()),
+            Err(e) => Err(e)
+        }
+    }
+
+    /// Writes this INI file to the given path.
+    ///
+    /// If the file already exists, it is first copied to a backup file with `.bak` appended to its name. The new contents then replace the original atomically, see `util::write_atomic`.
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let path = path.as_ref();
+        if path.exists() {
+            fs::copy(path, append_extension(path, "bak"))?;
+        } else if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        util::write_atomic(path, self.to_string())
+    }
+
+    /// Returns the index range of the lines belonging to the given section, not including the section header.
+    ///
+    /// Entries before the first section header belong to the section `General`, as in Qt. If there are none, an explicit `[General]` section is used instead.
+    fn section_range(&self, section: &str) -> Option<(usize, usize)> {
+        let first_header = self.lines.iter().position(|line| match *line { Line::Section { .. } => true, _ => false }).unwrap_or(self.lines.len());
+        let has_implicit_general = self.lines[..first_header].iter().any(|line| match *line { Line::Entry { .. } => true, _ => false });
+        let mut start = if section == "General" && has_implicit_general { Some(0) } else { None };
+        for (idx, line) in self.lines.iter().enumerate() {
+            if let Line::Section { ref name, .. } = *line {
+                if let Some(start) = start { return Some((start, idx)); }
+                if name == section { start = Some(idx + 1); }
+            }
+        }
+        start.map(|start| (start, self.lines.len()))
+    }
+
+    fn entry_idx(&self, section: &str, key: &str) -> Option<usize> {
+        let (start, end) = self.section_range(section)?;
+        (start..end).find(|&idx| match self.lines[idx] {
+            Line::Entry { key: ref entry_key, .. } => entry_key == key,
+            _ => false
+        })
+    }
+
+    /// Returns the value of the given key in the given section as it appears in the file.
+    fn get_raw(&self, section: &str, key: &str) -> Option<&str> {
+        match self.lines[self.entry_idx(section, key)?] {
+            Line::Entry { ref raw, .. } => Some(raw[raw.find('=').expect("entry without =") + 1..].trim()),
+            _ => unreachable!()
+        }
+    }
+
+    /// Returns the value of the given key in the given section, with quotes and escapes removed.
+    pub fn get(&self, section: &str, key: &str) -> Option<String> {
+        self.get_raw(section, key).map(unquote)
+    }
+
+    /// Returns the value of the given key in the given section as a list of strings, as stored by Qt for `QStringList` values.
+    pub fn get_list(&self, section: &str, key: &str) -> Option<Vec<String>> {
+        let raw = self.get_raw(section, key)?;
+        if raw.is_empty() { return Some(Vec::default()); }
+        let mut items = Vec::default();
+        let mut item = String::default();
+        let mut in_quotes = false;
+        let mut escaped = false;
+        for c in raw.chars() {
+            if escaped {
+                item.push(c);
+                escaped = false;
+            } else if c == '\\' && in_quotes {
+                item.push(c);
+                escaped = true;
+            } else if c == '"' {
+                item.push(c);
+                in_quotes = !in_quotes;
+            } else if c == ',' && !in_quotes {
+                items.push(unquote(item.trim()));
+                item.clear();
+            } else {
+                item.push(c);
+            }
+        }
+        items.push(unquote(item.trim()));
+        Some(items)
+    }
+
+    /// Sets the value of the given key in the given section.
+    ///
+    /// An existing entry is modified in place. Otherwise, the entry is added after the last entry of the section, creating the section at the end of the file if necessary.
+    pub fn set(&mut self, section: &str, key: &str, value: impl ToString) {
+        self.set_raw(section, key, quote(&value.to_string()));
+    }
+
+    /// Sets the value of the given key in the given section to a list of strings, in the format Qt uses for `QStringList` values.
+    pub fn set_list(&mut self, section: &str, key: &str, values: &[impl AsRef<str>]) {
+        self.set_raw(section, key, values.iter().map(|value| quote(value.as_ref())).collect::<Vec<_>>().join(", "));
+    }
+
+    fn set_raw(&mut self, section: &str, key: &str, raw_value: String) {
+        let line = Line::Entry {
+            key: key.to_owned(),
+            raw: format!("{}={}", key, raw_value)
+        };
+        if let Some(idx) = self.entry_idx(section, key) {
+            self.lines[idx] = line;
+        } else if let Some((start, end)) = self.section_range(section) {
+            let insert_idx = (start..end).rev()
+                .find(|&idx| match self.lines[idx] { Line::Other(ref raw) => !raw.trim().is_empty(), _ => true })
+                .map_or(start, |idx| idx + 1);
+            self.lines.insert(insert_idx, line);
+        } else {
+            if self.lines.last().map_or(false, |last| match *last { Line::Other(ref raw) => !raw.trim().is_empty(), _ => true }) {
+                self.lines.push(Line::Other(String::default()));
+            }
+            self.lines.push(Line::Section {
+                name: section.to_owned(),
+                raw: format!("[{}]", section)
+            });
+            self.lines.push(line);
+        }
+    }
+
+    /// Removes the given key from the given section, returning its previous value.
+    pub fn remove(&mut self, section: &str, key: &str) -> Option<String> {
+        let value = self.get(section, key)?;
+        self.lines.remove(self.entry_idx(section, key)?);
+        Some(value)
+    }
+}
+
+impl fmt::Display for Ini {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for line in &self.lines {
+            match *line {
+                Line::Section { ref raw, .. } | Line::Entry { ref raw, .. } | Line::Other(ref raw) => { write!(f, "{}{}", raw, if self.crlf { "\r\n" } else { "\n" })?; }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn append_extension(path: &Path, extension: &str) -> PathBuf {
+    let mut path = path.as_os_str().to_owned();
+    path.push(".");
+    path.push(extension);
+    path.into()
+}
+
+/// Removes the quotes and escapes Qt adds to string values.
+fn unquote(value: &str) -> String {
+    if value.len() < 2 || !value.starts_with('"') || !value.ends_with('"') { return value.to_owned(); }
+    let mut unquoted = String::default();
+    let mut chars = value[1..value.len() - 1].chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            match chars.next() {
+                Some('n') => unquoted.push('\n'),
+                Some('t') => unquoted.push('\t'),
+                Some(c) => unquoted.push(c),
+                None => unquoted.push('\\')
+            }
+        } else {
+            unquoted.push(c);
+        }
+    }
+    unquoted
+}
+
+/// Quotes and escapes a string value the way Qt does, if necessary.
+fn quote(value: &str) -> String {
+    if !value.starts_with(char::is_whitespace) && !value.ends_with(char::is_whitespace) && !value.contains(|c| "\";,=\\\n\t".contains(c)) && !value.starts_with('@') {
+        return value.to_owned();
+    }
+    let mut quoted = String::from("\"");
+    for c in value.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\t' => quoted.push_str("\\t"),
+            c => quoted.push(c)
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Cockatrice's main settings file, `settings/global.ini`, with accessors for the settings Lore Seeker Desktop manages.
+#[derive(Debug, Clone)]
+pub struct GlobalSettings {
+    path: PathBuf,
+    /// The underlying INI file, for accessing other settings.
+    pub ini: Ini
+}
+
+impl GlobalSettings {
+    /// Reads the `global.ini` file for the given Cockatrice paths. A missing file is treated as empty.
+    pub fn open(paths: &Paths) -> io::Result<GlobalSettings> {
+        let path = paths.global_ini();
+        Ok(GlobalSettings {
+            ini: Ini::open(&path)?,
+            path
+        })
+    }
+
+    /// Writes the settings back to `global.ini`, backing up the previous version to `global.ini.bak`.
+    pub fn save(&self) -> io::Result<()> {
+        self.ini.save(&self.path)
+    }
+
+    /// The card database Cockatrice loads, if configured.
+    pub fn card_database(&self) -> Option<PathBuf> {
+        self.ini.get("paths", "carddatabase").map(PathBuf::from)
+    }
+
+    /// Sets the card database Cockatrice loads.
+    pub fn set_card_database(&mut self, path: impl AsRef<Path>) {
+        self.ini.set("paths", "carddatabase", qt_path(path.as_ref()));
+    }
+
+    /// The token database Cockatrice loads, if configured.
+    pub fn token_database(&self) -> Option<PathBuf> {
+        self.ini.get("paths", "tokendatabase").map(PathBuf::from)
+    }
+
+    /// Sets the token database Cockatrice loads.
+    pub fn set_token_database(&mut self, path: impl AsRef<Path>) {
+        self.ini.set("paths", "tokendatabase", qt_path(path.as_ref()));
+    }
+
+    /// The folder from which Cockatrice loads custom sets, if configured.
+    pub fn custom_sets(&self) -> Option<PathBuf> {
+        self.ini.get("paths", "customsets").map(PathBuf::from)
+    }
+
+    /// Sets the folder from which Cockatrice loads custom sets.
+    pub fn set_custom_sets(&mut self, path: impl AsRef<Path>) {
+        self.ini.set("paths", "customsets", qt_path(path.as_ref()));
+    }
+}
+
+/// Formats a path the way Qt stores it, with forward slashes on all platforms.
+fn qt_path(path: &Path) -> String {
+    let path = path.to_string_lossy();
+    if cfg!(windows) { path.replace('\\', "/") } else { path.into_owned() }
+}
+
+/// Cockatrice's picture download settings, `settings/downloads.ini`.
+#[derive(Debug, Clone)]
+pub struct DownloadSettings {
+    path: PathBuf,
+    /// The underlying INI file, for accessing other settings.
+    pub ini: Ini
+}
+
+impl DownloadSettings {
+    /// Wraps the given contents of a `downloads.ini` file, which `save` writes to the given path.
+    pub fn new(path: impl Into<PathBuf>, ini: Ini) -> DownloadSettings {
+        DownloadSettings {
+            path: path.into(),
+            ini
+        }
+    }
+
+    /// Reads the `downloads.ini` file for the given Cockatrice paths. A missing file is treated as empty.
+    pub fn open(paths: &Paths) -> io::Result<DownloadSettings> {
+        let path = paths.downloads_ini();
+        Ok(DownloadSettings::new(&path, Ini::open(&path)?))
+    }
+
+    /// Writes the settings back to `downloads.ini`, backing up the previous version to `downloads.ini.bak`.
+    pub fn save(&self) -> io::Result<()> {
+        self.ini.save(&self.path)
+    }
+
+    /// The URL templates Cockatrice tries, in order, when downloading a card picture.
+    ///
+    /// If this is `None`, Cockatrice uses its built-in defaults.
+    pub fn urls(&self) -> Option<Vec<String>> {
+        self.ini.get_list("downloads", "urls")
+    }
+
+    /// Sets the URL templates Cockatrice tries, in order, when downloading a card picture.
+    pub fn set_urls(&mut self, urls: &[impl AsRef<str>]) {
+        self.ini.set_list("downloads", "urls", urls);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static GLOBAL_INI: &'static str = "; written by Cockatrice\n[General]\nlang=en\n\n[paths]\ncarddatabase=/home/user/.local/share/Cockatrice/Cockatrice/cards.xml\nunknown=\"keep, me\"\n";
+
+    #[test]
+    fn preserve_untouched_lines() {
+        let mut ini = Ini::parse(GLOBAL_INI);
+        assert_eq!(ini.to_string(), GLOBAL_INI);
+        ini.set("paths", "carddatabase", "/tmp/cards.xml");
+        assert_eq!(ini.to_string(), GLOBAL_INI.replace("/home/user/.local/share/Cockatrice/Cockatrice/cards.xml", "/tmp/cards.xml"));
+    }
+
+    #[test]
+    fn leading_general_section() {
+        let mut ini = Ini::parse(GLOBAL_INI);
+        assert_eq!(ini.get("General", "lang"), Some("en".to_owned()));
+        ini.set("General", "lang", "de");
+        ini.set("General", "updatenotification", "false");
+        assert_eq!(ini.to_string(), "; written by Cockatrice\n[General]\nlang=de\nupdatenotification=false\n\n[paths]\ncarddatabase=/home/user/.local/share/Cockatrice/Cockatrice/cards.xml\nunknown=\"keep, me\"\n");
+        let mut ini = Ini::parse("lang=en\n[paths]\n");
+        assert_eq!(ini.get("General", "lang"), Some("en".to_owned()));
+        ini.set("General", "updatenotification", "false");
+        assert_eq!(ini.to_string(), "lang=en\nupdatenotification=false\n[paths]\n");
+    }
+
+    #[test]
+    fn missing_section() {
+        let mut ini = Ini::parse(GLOBAL_INI);
+        assert_eq!(ini.get("downloads", "urls"), None);
+        assert_eq!(ini.remove("downloads", "urls"), None);
+        ini.set_list("downloads", "urls", &["https://example.com/!name!.png"]);
+        assert_eq!(ini.to_string(), format!("{}\n[downloads]\nurls=https://example.com/!name!.png\n", GLOBAL_INI));
+        let mut ini = Ini::default();
+        ini.set("General", "lang", "en");
+        assert_eq!(ini.to_string(), "[General]\nlang=en\n");
+    }
+
+    #[test]
+    fn quoted_values() {
+        let mut ini = Ini::parse(GLOBAL_INI);
+        assert_eq!(ini.get("paths", "unknown"), Some("keep, me".to_owned()));
+        ini.set("paths", "equals", "a=b");
+        ini.set_list("paths", "list", &["one, two", "three", "x=\"y\""]);
+        assert!(ini.to_string().contains("equals=\"a=b\"\nlist=\"one, two\", three, \"x=\\\"y\\\"\"\n"));
+        let ini = Ini::parse(&ini.to_string());
+        assert_eq!(ini.get("paths", "equals"), Some("a=b".to_owned()));
+        assert_eq!(ini.get_list("paths", "list"), Some(vec!["one, two".to_owned(), "three".to_owned(), "x=\"y\"".to_owned()]));
+    }
+
+    #[test]
+    fn crlf() {
+        let text = GLOBAL_INI.replace('\n', "\r\n");
+        let mut ini = Ini::parse(&text);
+        assert_eq!(ini.get("General", "lang"), Some("en".to_owned()));
+        assert_eq!(ini.to_string(), text);
+        ini.set("paths", "customsets", "/tmp/customsets");
+        assert!(ini.to_string().contains("unknown=\"keep, me\"\r\ncustomsets=/tmp/customsets\r\n"));
+        assert!(!ini.to_string().replace("\r\n", "").contains('\n'));
+    }
+
+    #[test]
+    fn save_with_backup() {
+        let dir = tempfile::tempdir().expect("failed to create temp dir");
+        let path = dir.path().join("settings").join("global.ini");
+        let mut ini = Ini::open(&path).expect("failed to open missing file");
+        ini.set("General", "lang", "en");
+        ini.save(&path).expect("failed to save new file");
+        assert!(!append_extension(&path, "bak").exists());
+        let mut ini = Ini::open(&path).expect("failed to open file");
+        ini.set("General", "lang", "de");
+        ini.save(&path).expect("failed to save file");
+        assert_eq!(fs::read_to_string(append_extension(&path, "bak")).expect("failed to read backup"), "[General]\nlang=en\n");
+        assert_eq!(fs::read_to_string(&path).expect("failed to read file"), "[General]\nlang=de\n");
+    }
+}
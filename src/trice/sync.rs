@@ -0,0 +1,150 @@
+//! Syncing Lore Seeker custom sets into Cockatrice's card database.
+
+use std::{
+    fmt,
+    fs,
+    io
+};
+use wrapped_enum::wrapped_enum;
+use crate::{
+    lore_seeker::Server,
+    util
+};
+use super::{
+    carddb::{
+        self,
+        CardDatabase,
+        Diff
+    },
+    paths::Paths,
+    pictures,
+    tokens
+};
+
+/// The path of the Lore Seeker custom set card database on the server, in Cockatrice format.
+static CARD_DB_PATH: &'static str = "/cockatrice/custom-sets.xml";
+/// The file name used for the synced card database in Cockatrice's `customsets` folder.
+pub static FILENAME: &'static str = "lore-seeker.xml";
+
+wrapped_enum! {
+    /// An error that can occur while syncing Cockatrice files.
+    #[derive(Debug)]
+    pub enum Error {
+        #[allow(missing_docs)]
+        CardDb(carddb::Error),
+        #[allow(missing_docs)]
+        Io(io::Error),
+        #[allow(missing_docs)]
+        Reqwest(reqwest::Error)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::CardDb(ref e) => e.fmt(f),
+            Error::Io(ref e) => e.fmt(f),
+            Error::Reqwest(ref e) => e.fmt(f)
+        }
+    }
+}
+
+/// The changes made by `sync`.
+#[derive(Debug, Default)]
+pub struct SyncReport {
+    /// The sets that were added, changed, or removed.
+    pub sets: Diff,
+    /// The names of the tokens that were added to `tokens.xml` or modified there.
+    pub tokens: Vec<String>,
+    /// Whether Cockatrice's card picture sources were changed, see `pictures::register`.
+    pub pictures: bool
+}
+
+impl SyncReport {
+    /// Returns `true` if the sync did not modify any sets, tokens, or picture sources.
+    pub fn is_empty(&self) -> bool {
+        self.sets.is_empty() && self.tokens.is_empty() && !self.pictures
+    }
+}
+
+impl fmt::Display for SyncReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_empty() { return self.sets.fmt(f); }
+        let mut lines = Vec::default();
+        if !self.sets.is_empty() { lines.push(self.sets.to_string()); }
+        if !self.tokens.is_empty() { lines.push(format!("Updated {}: {}", if self.tokens.len() == 1 { "token" } else { "tokens" }, self.tokens.join(", "))); }
+        if self.pictures { lines.push("Updated Cockatrice's card picture sources.".to_owned()); }
+        write!(f, "{}", lines.join("\n"))
+    }
+}
+
+/// Downloads the custom set card database from the given Lore Seeker server and saves it in Cockatrice's `customsets` folder.
+///
+/// The file is only replaced if its contents changed. Tokens from the custom sets are merged into Cockatrice's `tokens.xml`, see `tokens::sync`. The server is registered as Cockatrice's first card picture source, see `pictures::register`, unless it requires an auth token, which Cockatrice can't send. In that case, any registered Lore Seeker server is removed instead.
+pub fn sync(client: &reqwest::Client, server: &Server, paths: &Paths) -> Result<SyncReport, Error> {
+    let mut new_db = Vec::default();
+    server.get(client, CARD_DB_PATH).send()?.error_for_status()?.copy_to(&mut new_db)?;
+    let new_cards = CardDatabase::from_reader(&new_db[..])?;
+    let customsets_dir = paths.customsets_dir();
+    let path = customsets_dir.join(FILENAME);
+    let old_cards = if path.exists() { CardDatabase::open(&path)? } else { CardDatabase::default() };
+    let diff = old_cards.diff(&new_cards);
+    if !diff.is_empty() || !path.exists() {
+        fs::create_dir_all(&customsets_dir)?;
+        util::write_atomic(path, &new_db)?;
+    }
+    let tokens = tokens::sync(paths, &new_cards)?;
+    let pictures = if server.auth_token.is_none() { pictures::register(paths, server, 0)? } else { pictures::unregister(paths)? };
+    Ok(SyncReport { sets: diff, tokens, pictures })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        test_server::{
+            response,
+            serve
+        },
+        util
+    };
+    use super::{
+        *,
+        super::settings::DownloadSettings
+    };
+
+    static FIXTURE: &'static str = include_str!("../../tests/fixtures/carddb-v4.xml");
+
+    #[test]
+    fn sync_sends_auth_token() {
+        let (base_url, server) = serve(vec![response("200 OK", &["Content-Type: application/xml"], FIXTURE.len(), FIXTURE.as_bytes())]);
+        let server_config = Server { base_url: format!("{}/", base_url), auth_token: Some("secret".to_owned()) };
+        let data_dir = tempfile::tempdir().expect("failed to create temp dir");
+        let paths = Paths::new(data_dir.path());
+        let report = sync(&util::client().expect("failed to create client"), &server_config, &paths).expect("sync failed");
+        assert!(!report.sets.is_empty());
+        assert_eq!(fs::read_to_string(paths.customsets_dir().join(FILENAME)).expect("failed to read synced card database"), FIXTURE);
+        let requests = server.join().expect("test server panicked");
+        assert_eq!(requests[0].path(), CARD_DB_PATH);
+        assert_eq!(requests[0].header("authorization"), Some("Bearer secret"));
+    }
+
+    #[test]
+    fn sync_registers_pictures() {
+        let (base_url, server) = serve(vec![
+            response("200 OK", &["Content-Type: application/xml"], FIXTURE.len(), FIXTURE.as_bytes()),
+            response("200 OK", &["Content-Type: application/xml"], FIXTURE.len(), FIXTURE.as_bytes())
+        ]);
+        let mut server_config = Server { base_url, auth_token: None };
+        let data_dir = tempfile::tempdir().expect("failed to create temp dir");
+        let paths = Paths::new(data_dir.path());
+        let client = util::client().expect("failed to create client");
+        assert!(sync(&client, &server_config, &paths).expect("sync failed").pictures);
+        let urls = DownloadSettings::open(&paths).expect("failed to read downloads.ini").urls().expect("no picture sources");
+        assert_eq!(urls[0], pictures::pic_url(&server_config));
+        server_config.auth_token = Some("secret".to_owned());
+        assert!(sync(&client, &server_config, &paths).expect("sync failed").pictures);
+        let urls = DownloadSettings::open(&paths).expect("failed to read downloads.ini").urls().expect("no picture sources");
+        assert!(!urls.contains(&pictures::pic_url(&server_config)));
+        server.join().expect("test server panicked");
+    }
+}
@@ -0,0 +1,146 @@
+//! Generating Cockatrice token definitions (`tokens.xml`) for Lore Seeker custom sets.
+
+use super::{
+    carddb::{
+        self,
+        Card,
+        CardDatabase,
+        Related
+    },
+    paths::Paths
+};
+
+/// Extracts the tokens from a card database into a token database.
+///
+/// Each token gets a `<reverse-related>` entry for every card that lists it as `<related>`, so Cockatrice can offer to create the token from those cards.
+pub fn export(db: &CardDatabase) -> CardDatabase {
+    let mut tokens = db.cards.iter()
+        .filter(|card| card.token)
+        .cloned()
+        .collect::<Vec<_>>();
+    for card in db.cards.iter().filter(|card| !card.token) {
+        for related in &card.related {
+            if let Some(token) = tokens.iter_mut().find(|token| token.name == related.name) {
+                if !token.reverse_related.iter().any(|reverse| reverse.name == card.name) {
+                    token.reverse_related.push(Related {
+                        name: card.name.clone(),
+                        attributes: related.attributes.clone()
+                    });
+                }
+            }
+        }
+    }
+    let sets = db.sets.iter()
+        .filter(|set| tokens.iter().any(|token| token.printing(&set.name).is_some()))
+        .cloned()
+        .collect();
+    CardDatabase {
+        info: db.info.clone(),
+        sets,
+        cards: tokens,
+        other: Vec::default()
+    }
+}
+
+/// Merges the tokens from `new` into `existing`, keeping tokens which are only in `existing`.
+///
+/// If a token with the same name already exists and all of its printings are in `lore_seeker_sets`, it was added by a previous sync and is replaced, so changes to a custom token's text or P/T are picked up. Otherwise, its printings and reverse-related cards are extended with those from `new`, and its other data is left untouched, so standard tokens like Soldier aren't replaced by a custom set's version.
+///
+/// Returns the names of the tokens that were added or modified.
+pub fn merge(existing: &mut CardDatabase, new: CardDatabase, lore_seeker_sets: &[&str]) -> Vec<String> {
+    for set in new.sets {
+        if existing.set(&set.name).is_none() { existing.sets.push(set); }
+    }
+    let mut modified = Vec::default();
+    for token in new.cards {
+        let token = Card { token: true, ..token };
+        match existing.cards.iter_mut().find(|card| card.name == token.name) {
+            Some(card) => if !card.sets.is_empty() && card.sets.iter().all(|printing| lore_seeker_sets.contains(&&printing.code[..])) {
+                if *card != token {
+                    modified.push(token.name.clone());
+                    *card = token;
+                }
+            } else {
+                let mut changed = false;
+                for printing in token.sets {
+                    if card.printing(&printing.code).is_none() { card.sets.push(printing); changed = true; }
+                }
+                for reverse in token.reverse_related {
+                    if !card.reverse_related.iter().any(|existing_reverse| existing_reverse.name == reverse.name) {
+                        card.reverse_related.push(reverse);
+                        changed = true;
+                    }
+                }
+                if changed { modified.push(card.name.clone()); }
+            },
+            None => {
+                modified.push(token.name.clone());
+                existing.cards.push(token);
+            }
+        }
+    }
+    modified
+}
+
+/// Merges the tokens from the given card database into Cockatrice's `tokens.xml` file, creating it if it doesn't exist.
+///
+/// Returns the names of the tokens that were added or modified. The file is only written if this list is nonempty.
+pub fn sync(paths: &Paths, db: &CardDatabase) -> Result<Vec<String>, carddb::Error> {
+    let path = paths.tokens_xml();
+    let mut tokens = if path.exists() { CardDatabase::open(&path)? } else { CardDatabase::default() };
+    let lore_seeker_sets = db.sets.iter().map(|set| &set.name[..]).collect::<Vec<_>>();
+    let modified = merge(&mut tokens, export(db), &lore_seeker_sets);
+    if !modified.is_empty() { tokens.save(&path)?; }
+    Ok(modified)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn token(name: &str, set: &str, pt: &str) -> Card {
+        let mut card = Card::new(name);
+        card.token = true;
+        card.set_prop("pt", pt);
+        card.sets.push(carddb::Printing { code: set.to_owned(), attributes: Vec::default() });
+        card
+    }
+
+    fn db(cards: Vec<Card>) -> CardDatabase {
+        CardDatabase { cards, ..CardDatabase::default() }
+    }
+
+    #[test]
+    fn replace_lore_seeker_tokens() {
+        let mut existing = db(vec![token("Elf Warrior", "TVLN", "1/1")]);
+        let modified = merge(&mut existing, db(vec![token("Elf Warrior", "TVLN", "2/2")]), &["VLN", "TVLN"]);
+        assert_eq!(modified, vec![format!("Elf Warrior")]);
+        assert_eq!(existing.cards.len(), 1);
+        assert_eq!(existing.cards[0].pt(), Some("2/2"));
+        assert!(merge(&mut existing, db(vec![token("Elf Warrior", "TVLN", "2/2")]), &["VLN", "TVLN"]).is_empty());
+    }
+
+    #[test]
+    fn extend_other_tokens() {
+        let mut existing = db(vec![token("Soldier", "TDOM", "1/1")]);
+        let mut new_soldier = token("Soldier", "TVLN", "2/2");
+        new_soldier.reverse_related.push(Related::new("Grove Warden"));
+        let modified = merge(&mut existing, db(vec![new_soldier]), &["VLN", "TVLN"]);
+        assert_eq!(modified, vec![format!("Soldier")]);
+        let soldier = &existing.cards[0];
+        assert_eq!(soldier.pt(), Some("1/1"));
+        assert!(soldier.printing("TDOM").is_some());
+        assert!(soldier.printing("TVLN").is_some());
+        assert_eq!(soldier.reverse_related, vec![Related::new("Grove Warden")]);
+    }
+
+    #[test]
+    fn export_reverse_related() {
+        let mut creature = Card::new("Grove Warden");
+        creature.related.push(Related::new("Elf Warrior"));
+        creature.sets.push(carddb::Printing { code: format!("VLN"), attributes: Vec::default() });
+        let tokens = export(&db(vec![creature, token("Elf Warrior", "TVLN", "1/1")]));
+        assert_eq!(tokens.cards.len(), 1);
+        assert_eq!(tokens.cards[0].reverse_related, vec![Related::new("Grove Warden")]);
+    }
+}
@@ -1,33 +1,109 @@
 //! Functions for handling self-updates and updates of Cockatrice files.
 
 use std::{
+    cmp::Ordering::*,
+    env,
+    ffi::OsString,
     fmt,
-    fs::File,
-    io,
-    path::Path
+    fs::{
+        self,
+        File
+    },
+    io::{
+        self,
+        prelude::*
+    },
+    path::{
+        Path,
+        PathBuf
+    },
+    process::{
+        Command,
+        ExitStatus
+    },
+    thread,
+    time::{
+        Duration,
+        Instant
+    }
 };
 use itertools::Itertools;
+use semver::{
+    SemVerError,
+    Version
+};
+use serde_derive::{
+    Deserialize,
+    Serialize
+};
 use crate::{
-    github::Repo,
+    checksum::{
+        self,
+        HashingWriter
+    },
+    config::Config,
+    delta,
+    github::{
+        Release,
+        Repo
+    },
+    signing::{
+        self,
+        ReleaseManifest
+    },
+    util,
     version
 };
 
-#[cfg(target_arch = "x86")]
-static PLATFORM_ASSET: &'static str = "lore-seeker-windows-32bit.exe";
-#[cfg(target_arch = "x86_64")]
-static PLATFORM_ASSET: &'static str = "lore-seeker-windows-64bit.exe";
+/// The release asset containing the build for this platform, or `None` if no builds are published for it.
+#[cfg(all(windows, target_arch = "x86"))]
+static PLATFORM_ASSET: Option<&'static str> = Some("lore-seeker-windows-32bit.exe");
+/// The release asset containing the build for this platform, or `None` if no builds are published for it.
+#[cfg(all(windows, target_arch = "x86_64"))]
+static PLATFORM_ASSET: Option<&'static str> = Some("lore-seeker-windows-64bit.exe");
+/// The release asset containing the build for this platform, or `None` if no builds are published for it.
+#[cfg(not(all(windows, any(target_arch = "x86", target_arch = "x86_64"))))]
+static PLATFORM_ASSET: Option<&'static str> = None;
+
+/// Whether releases include builds for this platform, i.e. whether `download_update` and `self_update` can succeed.
+pub fn is_supported() -> bool {
+    PLATFORM_ASSET.is_some()
+}
 
 /// An error that can occur in the GitHub API.
 #[derive(Debug)]
 pub enum Error {
+    /// An error occurred while downloading the update.
+    Download(util::DownloadError),
     /// The release asset we were looking for is not in the release.
     AssetNotFound,
+    /// The downloaded file's SHA-256 digest does not match the one published with the release.
+    ChecksumMismatch {
+        /// The digest listed in the release's checksum manifest.
+        expected: String,
+        /// The digest of the downloaded file.
+        actual: String
+    },
+    /// The release has no checksum for the asset we were looking for.
+    ChecksumMissing,
+    /// The running executable could not be located, so it can't be replaced.
+    CurrentExe(io::Error),
     /// An I/O error occurred.
     Io(io::Error),
+    /// The signed release manifest is for a different version or commit than the release it's attached to.
+    ManifestMismatch,
+    /// The selected update channel has no releases.
+    NoRelease,
+    /// The new version was installed but exited with the given status (or could not be started at all) immediately after being launched. The previous version has been restored.
+    RelaunchFailed(Option<ExitStatus>),
     /// An error occurred in the `reqwest` crate.
     Reqwest(reqwest::Error),
-    /// The latest release's tag name is not listed in the repo's tags.
-    TagNotFound
+    /// A version number could not be parsed.
+    SemVer(SemVerError),
+    /// The release manifest could not be verified.
+    Signing(signing::Error),
+    /// Releases don't include builds for this platform.
+    UnsupportedPlatform
 }
 
 impl From<io::Error> for Error {
@@ -42,42 +118,423 @@ impl From<reqwest::Error> for Error {
     }
 }
 
+impl From<util::DownloadError> for Error {
+    fn from(e: util::DownloadError) -> Error {
+        Error::Download(e)
+    }
+}
+
+impl From<SemVerError> for Error {
+    fn from(e: SemVerError) -> Error {
+        Error::SemVer(e)
+    }
+}
+
+impl From<signing::Error> for Error {
+    fn from(e: signing::Error) -> Error {
+        Error::Signing(e)
+    }
+}
+
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
             Error::AssetNotFound => write!(f, "Release asset not found."),
+            Error::Download(ref e) => e.fmt(f),
+            Error::ChecksumMismatch { ref expected, ref actual } => write!(f, "The downloaded file is corrupted (SHA-256 checksum {}, expected {}).", actual, expected),
+            Error::ChecksumMissing => write!(f, "The signed release manifest has no checksum for the update."),
+            Error::CurrentExe(ref e) => write!(f, "Could not locate the Lore Seeker Desktop executable: {}", e),
             Error::Io(ref e) => e.fmt(f),
+            Error::ManifestMismatch => write!(f, "The release signature does not match the release."),
+            Error::NoRelease => write!(f, "No release found for the selected update channel."),
+            Error::RelaunchFailed(Some(status)) => write!(f, "The new version of Lore Seeker Desktop failed to start ({}). The previous version has been restored.", status),
+            Error::RelaunchFailed(None) => write!(f, "The new version of Lore Seeker Desktop could not be started. The previous version has been restored."),
             Error::Reqwest(ref e) => e.fmt(f),
-            Error::TagNotFound => write!(f, "Release tag not found.")
+            Error::SemVer(ref e) => e.fmt(f),
+            Error::Signing(ref e) => e.fmt(f),
+            Error::UnsupportedPlatform => write!(f, "Updates are not available for this platform.")
+        }
+    }
+}
+
+/// The tag of the rolling release containing the newest build of the default branch.
+pub static NIGHTLY_TAG: &'static str = "nightly";
+
+/// Which kinds of releases the user wants to be offered as updates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UpdateChannel {
+    /// Only full releases.
+    Stable,
+    /// Full releases and pre-releases.
+    Beta,
+    /// The newest build, published as the release tagged `nightly`.
+    Nightly
+}
+
+impl Default for UpdateChannel {
+    fn default() -> UpdateChannel {
+        UpdateChannel::Stable
+    }
+}
+
+impl UpdateChannel {
+    /// All update channels, from most to least stable.
+    pub const ALL: [UpdateChannel; 3] = [UpdateChannel::Stable, UpdateChannel::Beta, UpdateChannel::Nightly];
+
+    /// The identifier used for this channel in the settings file. Matches the serialized form.
+    pub fn name(&self) -> &'static str {
+        match *self {
+            UpdateChannel::Stable => "stable",
+            UpdateChannel::Beta => "beta",
+            UpdateChannel::Nightly => "nightly"
+        }
+    }
+
+    /// Parses an identifier returned by `name`.
+    pub fn from_name(name: &str) -> Option<UpdateChannel> {
+        UpdateChannel::ALL.iter().cloned().find(|channel| channel.name() == name)
+    }
+
+    /// Returns the newest release on this channel in the given repository.
+    pub fn release(&self, client: &reqwest::Client, repo: &Repo) -> Result<Release, Error> {
+        Ok(match *self {
+            UpdateChannel::Stable => repo.latest_release(client)?,
+            UpdateChannel::Beta => repo.releases(client)? // newest first
+                .into_iter()
+                .find(|release| !release.draft && release.tag_name != NIGHTLY_TAG)
+                .ok_or(Error::NoRelease)?,
+            UpdateChannel::Nightly => repo.release_by_tag(client, NIGHTLY_TAG)?.ok_or(Error::NoRelease)?
+        })
+    }
+}
+
+impl fmt::Display for UpdateChannel {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            UpdateChannel::Stable => write!(f, "Stable"),
+            UpdateChannel::Beta => write!(f, "Beta"),
+            UpdateChannel::Nightly => write!(f, "Nightly")
+        }
+    }
+}
+
+/// Downloads and verifies the signed manifest of the given release, and checks that it belongs to that release.
+///
+/// The nightly release is tagged `nightly` rather than with its version number, so its tag isn't checked.
+fn verified_manifest(client: &reqwest::Client, release: &Release) -> Result<ReleaseManifest, Error> {
+    let manifest = ReleaseManifest::fetch_verified(client, release)?;
+    if release.tag_name != NIGHTLY_TAG && release.tag_name != format!("v{}", manifest.version) { return Err(Error::ManifestMismatch); }
+    Ok(manifest)
+}
+
+/// Downloads the newest release of Lore Seeker Desktop on the configured channel and saves it to the given path.
+///
+/// If releases don't include builds for this platform, `Error::UnsupportedPlatform` is returned.
+///
+/// The download is checked against the SHA-256 checksum in the release's signed manifest. If it doesn't match, `Error::ChecksumMismatch` is returned.
+///
+/// If the release has a patch from this version, it is applied to the running executable instead of downloading the full update. If that fails for any reason, the full update is downloaded.
+///
+/// The file is downloaded to `save_path` with `.part` appended, i.e. in the same directory so the final rename doesn't cross file systems. It is flushed to disk, made executable on Unix, and only moved to `save_path` once it's complete and verified, so an interrupted or corrupted download never replaces an existing file at `save_path`. If a previous download was interrupted, it is resumed. `progress` is called with the number of bytes downloaded so far and the total size, if known.
+pub fn download_update(client: &reqwest::Client, config: &Config, save_path: impl AsRef<Path>, progress: impl FnMut(u64, Option<u64>)) -> Result<(), Error> {
+    let platform_asset = PLATFORM_ASSET.ok_or(Error::UnsupportedPlatform)?;
+    let release = config.update_channel.release(client, &config.update_repo)?;
+    let manifest = verified_manifest(client, &release)?;
+    download_release(client, release, &manifest, platform_asset, save_path.as_ref(), progress)
+}
+
+/// Downloads the given asset of a release whose manifest has already been verified, as described for `download_update`.
+fn download_release(client: &reqwest::Client, release: Release, manifest: &ReleaseManifest, platform_asset: &str, save_path: &Path, progress: impl FnMut(u64, Option<u64>)) -> Result<(), Error> {
+    let expected = manifest.assets.get(platform_asset).ok_or(Error::ChecksumMissing)?.clone();
+    let mut partial_path = save_path.as_os_str().to_owned();
+    partial_path.push(".part");
+    let partial_path = PathBuf::from(partial_path);
+    // an existing partial file is an interrupted full download, which is resumed instead
+    let patched = !partial_path.exists() && match download_patch(client, &release, platform_asset, manifest, &expected, &partial_path) {
+        Ok(patched) => patched,
+        Err(e) => {
+            eprintln!("failed to apply update patch, downloading the full update instead: {}", e);
+            false
+        }
+    };
+    if !patched {
+        let download_url = {
+            let (asset,) = release.assets
+                .into_iter()
+                .filter(|asset| asset.name == platform_asset)
+                .collect_tuple().ok_or(Error::AssetNotFound)?;
+            asset.browser_download_url
+        };
+        util::download(client, &download_url, &partial_path, progress)?;
+    }
+    finish_download(&partial_path, expected, save_path)
+}
+
+/// Checks the downloaded file at `partial_path` against the `expected` SHA-256 digest, and moves it to `save_path` if it matches.
+///
+/// If it doesn't match, the file is deleted and `save_path` is left untouched.
+fn finish_download(partial_path: &Path, expected: String, save_path: &Path) -> Result<(), Error> {
+    let actual = checksum::file_digest(partial_path)?;
+    if actual != expected {
+        fs::remove_file(partial_path)?;
+        return Err(Error::ChecksumMismatch { expected, actual });
+    }
+    #[cfg(unix)] {
+        use std::os::unix::fs::PermissionsExt as _;
+
+        fs::set_permissions(partial_path, fs::Permissions::from_mode(0o755))?;
+    }
+    fs::rename(partial_path, save_path)?;
+    #[cfg(unix)] {
+        // make sure the rename itself survives a crash
+        if let Some(parent) = save_path.parent() {
+            File::open(if parent.as_os_str().is_empty() { Path::new(".") } else { parent })?.sync_all()?;
         }
     }
+    Ok(())
 }
 
-/// Downloads the latest release of Lore Seeker Desktop and saves it to the given path.
-pub fn download_update(client: &reqwest::Client, save_path: impl AsRef<Path>) -> Result<(), Error> {
-    let download_url = {
-        let (asset,) = Repo::new("fenhl", "lore-seeker-desktop")
-            .latest_release(client)?
-            .assets
-            .into_iter()
-            .filter(|asset| &asset.name == PLATFORM_ASSET)
-            .collect_tuple().ok_or(Error::AssetNotFound)?;
-        asset.browser_download_url
+/// Tries to create the update at `path` by downloading a patch from the running version to `platform_asset` and applying it to the running executable.
+///
+/// Returns `Ok(false)` if there is no patch from this version, or if the patch or its result doesn't match the signed manifest, e.g. because this is a development build. In that case, or if an error is returned, nothing is left at `path`, so the caller can fall back to downloading the full update.
+fn download_patch(client: &reqwest::Client, release: &Release, platform_asset: &str, manifest: &ReleaseManifest, expected: &str, path: &Path) -> Result<bool, Error> {
+    let name = delta::patch_name(platform_asset, &Version::parse(env!("CARGO_PKG_VERSION"))?);
+    let patch_digest = match manifest.assets.get(&name) {
+        Some(digest) => digest,
+        None => { return Ok(false); }
+    };
+    let asset = match release.assets.iter().find(|asset| asset.name == name) {
+        Some(asset) => asset,
+        None => { return Ok(false); }
+    };
+    let mut writer = HashingWriter::new(Vec::default());
+    client.get(&asset.browser_download_url).send()?.error_for_status()?.copy_to(&mut writer)?;
+    let (patch, actual) = writer.finish();
+    if actual != *patch_digest { return Ok(false); }
+    let new = match delta::apply(&fs::read(env::current_exe()?)?, &patch) {
+        Ok(new) => new,
+        Err(_) => { return Ok(false); } // malformed patch, or patching an executable it wasn't created from
     };
-    let mut response = client.get(&download_url).send()?.error_for_status()?;
-    let mut save_file = File::open(save_path)?;
-    response.copy_to(&mut save_file)?;
+    let mut writer = HashingWriter::new(io::sink());
+    writer.write_all(&new)?;
+    if writer.finish().1 != expected { return Ok(false); }
+    let result = File::create(path).and_then(|mut file| {
+        file.write_all(&new)?;
+        file.sync_all()
+    });
+    if let Err(e) = result {
+        let _ = fs::remove_file(path);
+        return Err(e.into());
+    }
+    Ok(true)
+}
+
+/// How long a relaunched executable has to keep running for the update to be considered successful.
+const RELAUNCH_GRACE_PERIOD: Duration = Duration::from_secs(5);
+
+/// Returns a path in the same directory as `exe` with the given suffix appended to the file name.
+fn sibling(exe: &Path, prefix: &str, suffix: &str) -> PathBuf {
+    let mut name = OsString::from(prefix);
+    name.push(exe.file_name().unwrap_or_default());
+    name.push(suffix);
+    exe.with_file_name(name)
+}
+
+/// The path where the previous version of the executable is kept after an update, so it can be restored if the new version fails to start.
+fn backup_path(exe: &Path) -> PathBuf {
+    sibling(exe, "", ".old")
+}
+
+/// Downloads the newest release of Lore Seeker Desktop on the configured channel, replaces the running executable with it, and starts the new version.
+///
+/// The update is downloaded to a staging file beside the running executable, so that it can be moved into place without copying. The running executable is kept as a backup until the new version has been running for a few seconds. If the new version can't be started or exits with an error during that time, the backup is restored and `Error::RelaunchFailed` is returned.
+///
+/// On success, the caller should exit as soon as possible. The new version removes the backup on startup, see `remove_backup`.
+pub fn self_update(client: &reqwest::Client, config: &Config, progress: impl FnMut(u64, Option<u64>)) -> Result<(), Error> {
+    if !is_supported() { return Err(Error::UnsupportedPlatform); }
+    let exe = env::current_exe().and_then(fs::canonicalize).map_err(Error::CurrentExe)?;
+    let staging = sibling(&exe, ".", ".new");
+    let backup = backup_path(&exe);
+    download_update(client, config, &staging, progress)?;
+    swap(&exe, &staging, &backup)?;
+    match relaunch(&exe) {
+        Ok(()) => Ok(()),
+        Err(status) => {
+            restore(&exe, &backup)?;
+            Err(Error::RelaunchFailed(status))
+        }
+    }
+}
+
+/// Moves the executable at `staging` to `exe`, keeping the old one at `backup`.
+#[cfg(unix)]
+fn swap(exe: &Path, staging: &Path, backup: &Path) -> io::Result<()> {
+    if backup.exists() { fs::remove_file(backup)?; }
+    fs::hard_link(exe, backup)?;
+    fs::rename(staging, exe) // atomic, the running process keeps its inode
+}
+
+/// Moves the executable at `staging` to `exe`, keeping the old one at `backup`.
+#[cfg(not(unix))]
+fn swap(exe: &Path, staging: &Path, backup: &Path) -> io::Result<()> {
+    if backup.exists() { fs::remove_file(backup)?; }
+    fs::rename(exe, backup)?; // a running executable can't be replaced or deleted on Windows, but it can be renamed
+    if let Err(e) = fs::rename(staging, exe) {
+        fs::rename(backup, exe)?;
+        return Err(e);
+    }
+    Ok(())
+}
+
+/// Moves the executable at `backup` back to `exe`.
+fn restore(exe: &Path, backup: &Path) -> io::Result<()> {
+    #[cfg(not(unix))] { if exe.exists() { fs::remove_file(exe)?; } }
+    fs::rename(backup, exe)
+}
+
+/// Starts the executable at `exe` and waits for the grace period. Returns the exit status if it stopped with an error in that time, or `Err(None)` if it couldn't be started.
+fn relaunch(exe: &Path) -> Result<(), Option<ExitStatus>> {
+    let mut child = Command::new(exe).args(env::args_os().skip(1)).spawn().map_err(|_| None)?;
+    let start = Instant::now();
+    while start.elapsed() < RELAUNCH_GRACE_PERIOD {
+        match child.try_wait() {
+            Ok(Some(status)) if status.success() => { return Ok(()); }
+            Ok(Some(status)) => { return Err(Some(status)); }
+            Ok(None) => { thread::sleep(Duration::from_millis(100)); }
+            Err(_) => { return Ok(()); } // can't observe the child, assume it's running
+        }
+    }
     Ok(())
 }
 
-/// Returns `Ok(true)` if Lore Seeker Desktop is up to date, or `Ok(false)` if an update is available.
-pub fn update_check(client: &reqwest::Client) -> Result<bool, Error> {
-    let repo = Repo::new("fenhl", "lore-seeker-desktop");
-    let tag_name = repo.latest_release(&client)?.tag_name;
-    let current_hash = if let Some((tag,)) = repo.tags(&client)?.into_iter().filter(|tag| tag.name == tag_name).collect_tuple() {
-        tag.commit.sha
-    } else {
-        return Err(Error::TagNotFound);
+/// Deletes the previous version of the executable left behind by `self_update`, if any.
+///
+/// This should be called on startup, on a background thread. The previous version may still be waiting to see whether this one keeps running, and restores the backup if it doesn't, so this blocks until the grace period is over before deleting it. On Windows, the previous version may take a little longer to exit, so this retries for another grace period after that.
+pub fn remove_backup() -> io::Result<()> {
+    let backup = backup_path(&env::current_exe().and_then(fs::canonicalize)?);
+    if !backup.exists() { return Ok(()); }
+    thread::sleep(RELAUNCH_GRACE_PERIOD + Duration::from_secs(1));
+    let start = Instant::now();
+    loop {
+        match fs::remove_file(&backup) {
+            Ok(()) => { return Ok(()); }
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => { return Ok(()); }
+            Err(_) if start.elapsed() < RELAUNCH_GRACE_PERIOD => { thread::sleep(Duration::from_millis(500)); }
+            Err(e) => { return Err(e); }
+        }
+    }
+}
+
+/// The result of `update_check`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UpdateStatus {
+    /// This is the latest release.
+    UpToDate,
+    /// A newer release is available.
+    UpdateAvailable {
+        /// The version number of the latest release.
+        version: Version,
+        /// The commit hash the latest release was built from.
+        commit: String,
+        /// The release notes of the latest release, in Markdown.
+        notes: String
+    },
+    /// This is a newer version than the latest release, e.g. a development build.
+    AheadOfRelease
+}
+
+/// Compares the version of this build of Lore Seeker Desktop to the newest release on the configured channel.
+///
+/// If the version numbers are equal, the commit hashes are compared, and a build from a different commit than the release is considered ahead of it, since the version number is only bumped when releasing. The exception is the nightly channel, where the version number stays the same between builds, so any other commit is offered as an update. Releases without a valid signature are reported as errors rather than offered as updates.
+pub fn update_check(client: &reqwest::Client, config: &Config) -> Result<UpdateStatus, Error> {
+    let release = config.update_channel.release(client, &config.update_repo)?;
+    let manifest = verified_manifest(client, &release)?;
+    let latest = Version::parse(&manifest.version)?;
+    let current = Version::parse(env!("CARGO_PKG_VERSION"))?;
+    Ok(match current.cmp(&latest) {
+        Less => UpdateStatus::UpdateAvailable {
+            version: latest,
+            commit: manifest.commit,
+            notes: release.body
+        },
+        Equal if version::GIT_COMMIT_HASH == manifest.commit => UpdateStatus::UpToDate,
+        Equal if config.update_channel == UpdateChannel::Nightly => UpdateStatus::UpdateAvailable {
+            version: latest,
+            commit: manifest.commit,
+            notes: release.body
+        },
+        Equal | Greater => UpdateStatus::AheadOfRelease
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_server::{
+        response,
+        serve,
+        Request
     };
-    Ok(version::GIT_COMMIT_HASH == current_hash)
+    use super::*;
+
+    fn digest(data: &[u8]) -> String {
+        let mut writer = HashingWriter::new(io::sink());
+        writer.write_all(data).expect("failed to hash data");
+        writer.finish().1
+    }
+
+    #[test]
+    fn patch_checksum_mismatch() {
+        let new = b"new version".to_vec();
+        let patch_name = delta::patch_name("lore-seeker.exe", &Version::parse(env!("CARGO_PKG_VERSION")).expect("failed to parse package version"));
+        let (base_url, server) = serve(vec![
+            response("200 OK", &[], 9, b"corrupted"),
+            response("200 OK", &[], new.len(), &new)
+        ]);
+        let release = serde_json::from_value::<Release>(serde_json::json!({
+            "assets": [
+                {"name": "lore-seeker.exe", "browser_download_url": format!("{}/full", base_url)},
+                {"name": patch_name, "browser_download_url": format!("{}/patch", base_url)}
+            ],
+            "body": "",
+            "draft": false,
+            "id": 1,
+            "name": "Lore Seeker Desktop 0.3.0",
+            "prerelease": false,
+            "tag_name": "v0.3.0",
+            "upload_url": ""
+        })).expect("failed to parse release");
+        let manifest = ReleaseManifest {
+            version: "0.3.0".to_owned(),
+            commit: "0123456789abcdef0123456789abcdef01234567".to_owned(),
+            assets: vec![
+                ("lore-seeker.exe".to_owned(), digest(&new)),
+                (patch_name, digest(b"the real patch"))
+            ].into_iter().collect()
+        };
+        let dir = tempfile::tempdir().expect("failed to create temp dir");
+        let save_path = dir.path().join("lore-seeker.exe");
+        download_release(&util::client().expect("failed to create client"), release, &manifest, "lore-seeker.exe", &save_path, |_, _| ()).expect("download failed");
+        assert_eq!(fs::read(&save_path).expect("failed to read update"), new);
+        let requests = server.join().expect("test server panicked");
+        assert_eq!(requests.iter().map(Request::path).collect::<Vec<_>>(), vec!["/patch", "/full"]);
+    }
+
+    #[test]
+    fn checksum_mismatch() {
+        let (base_url, server) = serve(vec![response("200 OK", &[], 9, b"corrupted")]);
+        let dir = tempfile::tempdir().expect("failed to create temp dir");
+        let save_path = dir.path().join("lore-seeker.exe");
+        let partial_path = dir.path().join("lore-seeker.exe.part");
+        fs::write(&save_path, b"previous version").expect("failed to write existing file");
+        util::download(&util::client().expect("failed to create client"), &format!("{}/asset", base_url), &partial_path, |_, _| ()).expect("download failed");
+        server.join().expect("test server panicked");
+        let expected = checksum::file_digest(&save_path).expect("failed to hash existing file");
+        match finish_download(&partial_path, expected, &save_path) {
+            Err(Error::ChecksumMismatch { .. }) => {}
+            result => panic!("expected checksum mismatch, got {:?}", result)
+        }
+        assert_eq!(fs::read(&save_path).expect("failed to read existing file"), b"previous version");
+        assert!(!partial_path.exists());
+    }
 }
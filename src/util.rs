@@ -1,11 +1,21 @@
 //! Various utility functions.
 
 use std::{
-    fs::File,
+    fmt,
+    fs::{
+        self,
+        File,
+        OpenOptions
+    },
     io::{
         self,
         prelude::*
     },
+    path::{
+        Path,
+        PathBuf
+    },
+    thread,
     time::Duration
 };
 use azul::dialogs::{
@@ -29,6 +39,39 @@ wrapped_enum! {
     }
 }
 
+/// How often `download` retries after a network error before giving up.
+const DOWNLOAD_RETRIES: u32 = 5;
+
+wrapped_enum! {
+    /// An error that can occur in `download`.
+    #[derive(Debug)]
+    pub enum DownloadError {
+        #[allow(missing_docs)]
+        Io(io::Error),
+        #[allow(missing_docs)]
+        Reqwest(reqwest::Error)
+    }
+}
+
+impl DownloadError {
+    /// Returns `true` if retrying the download might help, i.e. the error is an I/O, network, or server error rather than a client error like 404.
+    fn is_transient(&self) -> bool {
+        match *self {
+            DownloadError::Io(_) => true,
+            DownloadError::Reqwest(ref e) => e.status().map_or(true, |status| status.is_server_error())
+        }
+    }
+}
+
+impl fmt::Display for DownloadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            DownloadError::Io(ref e) => e.fmt(f),
+            DownloadError::Reqwest(ref e) => e.fmt(f)
+        }
+    }
+}
+
 /// Returns a `reqwest::Client` identified as Lore Seeker Desktop via the `User-Agent` header.
 pub fn client() -> Result<reqwest::Client, reqwest::Error> {
     let mut headers = reqwest::header::HeaderMap::new();
@@ -36,6 +79,58 @@ pub fn client() -> Result<reqwest::Client, reqwest::Error> {
     Ok(reqwest::Client::builder().default_headers(headers).build()?)
 }
 
+/// Downloads the given URL into the file at the given path.
+///
+/// If the file already exists, it's treated as a partial download from a previous attempt and only the rest of the file is requested using an HTTP `Range` header. If the server doesn't support this, the file is downloaded from the start. Network errors are retried with exponential backoff, resuming where the previous attempt stopped.
+///
+/// `progress` is called with the number of bytes downloaded so far and the total size, if known. The file should be verified after downloading, and deleted if that fails so that the next attempt starts from scratch.
+pub fn download(client: &reqwest::Client, url: &str, path: impl AsRef<Path>, mut progress: impl FnMut(u64, Option<u64>)) -> Result<(), DownloadError> {
+    let path = path.as_ref();
+    let mut retries = 0;
+    loop {
+        match download_attempt(client, url, path, &mut progress) {
+            Ok(()) => { return Ok(()); }
+            Err(ref e) if retries < DOWNLOAD_RETRIES && e.is_transient() => {
+                thread::sleep(Duration::from_secs(1 << retries));
+                retries += 1;
+            }
+            Err(e) => { return Err(e); }
+        }
+    }
+}
+
+fn download_attempt(client: &reqwest::Client, url: &str, path: &Path, progress: &mut impl FnMut(u64, Option<u64>)) -> Result<(), DownloadError> {
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    let mut downloaded = file.metadata()?.len();
+    let mut request = client.get(url);
+    if downloaded > 0 { request = request.header(reqwest::header::RANGE, format!("bytes={}-", downloaded)); }
+    let mut response = request.send()?;
+    match response.status() {
+        reqwest::StatusCode::PARTIAL_CONTENT => {}
+        reqwest::StatusCode::RANGE_NOT_SATISFIABLE if downloaded > 0 => { return Ok(()); } // the previous attempt already downloaded the entire file
+        _ => {
+            response = response.error_for_status()?;
+            file.set_len(0)?;
+            downloaded = 0;
+        }
+    }
+    let total = response.content_length().map(|remaining| downloaded + remaining);
+    progress(downloaded, total);
+    let mut buf = [0; 64 * 1024];
+    loop {
+        let read = response.read(&mut buf)?;
+        if read == 0 { break; }
+        file.write_all(&buf[..read])?;
+        downloaded += read as u64;
+        progress(downloaded, total);
+    }
+    if total.map_or(false, |total| downloaded < total) {
+        return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "connection closed before the download was complete").into());
+    }
+    file.sync_all()?;
+    Ok(())
+}
+
 /// Displays an error message as a dialog, but returns normally after OK is clicked.
 pub fn error_message(title: &str, message: &str) {
     msg_box_ok(title, message, MessageBoxIcon::Error);
@@ -57,7 +152,91 @@ pub fn release_client() -> Result<reqwest::Client, ReleaseClientError> {
     Ok(reqwest::Client::builder().default_headers(headers).timeout(Duration::from_secs(600)).build()?)
 }
 
+/// Replaces the file at the given path with the given contents, such that a crash or power loss leaves either the old or the new contents.
+///
+/// The contents are written to a temporary file with `.tmp` appended to its name, which is flushed to disk and then renamed over the original. On Unix, the directory is also flushed so the rename itself is durable.
+pub fn write_atomic(path: impl AsRef<Path>, contents: impl AsRef<[u8]>) -> io::Result<()> {
+    let path = path.as_ref();
+    let mut tmp_path = path.as_os_str().to_owned();
+    tmp_path.push(".tmp");
+    let tmp_path = PathBuf::from(tmp_path);
+    {
+        let mut file = File::create(&tmp_path)?;
+        file.write_all(contents.as_ref())?;
+        file.sync_all()?;
+    }
+    fs::rename(&tmp_path, path)?;
+    #[cfg(unix)] {
+        if let Some(parent) = path.parent() {
+            File::open(if parent.as_os_str().is_empty() { Path::new(".") } else { parent })?.sync_all()?;
+        }
+    }
+    Ok(())
+}
+
 /// Asks the user a yes/no question and returns the answer.
 pub fn yesno(message: &str) -> bool {
     msg_box_yes_no("Lore Seeker", message, MessageBoxIcon::Question, Yes) == Yes
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::test_server::{
+        response,
+        serve
+    };
+    use super::*;
+
+    #[test]
+    fn full_download() {
+        let (base_url, server) = serve(vec![response("200 OK", &[], 11, b"hello world")]);
+        let dir = tempfile::tempdir().expect("failed to create temp dir");
+        let path = dir.path().join("download.part");
+        let mut progress = Vec::default();
+        download(&client().expect("failed to create client"), &format!("{}/asset", base_url), &path, |downloaded, total| progress.push((downloaded, total))).expect("download failed");
+        assert_eq!(fs::read(&path).expect("failed to read download"), b"hello world");
+        assert_eq!(progress.last(), Some(&(11, Some(11))));
+        let requests = server.join().expect("test server panicked");
+        assert_eq!(requests[0].path(), "/asset");
+        assert_eq!(requests[0].header("range"), None);
+    }
+
+    #[test]
+    fn resume_truncated_download() {
+        let (base_url, server) = serve(vec![
+            response("200 OK", &[], 11, b"hello"),
+            response("206 Partial Content", &["Content-Range: bytes 5-10/11"], 6, b" world")
+        ]);
+        let dir = tempfile::tempdir().expect("failed to create temp dir");
+        let path = dir.path().join("download.part");
+        let mut progress = Vec::default();
+        download(&client().expect("failed to create client"), &format!("{}/asset", base_url), &path, |downloaded, total| progress.push((downloaded, total))).expect("download failed");
+        assert_eq!(fs::read(&path).expect("failed to read download"), b"hello world");
+        assert_eq!(progress.last(), Some(&(11, Some(11))));
+        let requests = server.join().expect("test server panicked");
+        assert_eq!(requests[0].header("range"), None);
+        assert_eq!(requests[1].header("range"), Some("bytes=5-"));
+    }
+
+    #[test]
+    fn already_complete_download() {
+        let (base_url, server) = serve(vec![response("416 Range Not Satisfiable", &["Content-Range: bytes */11"], 0, b"")]);
+        let dir = tempfile::tempdir().expect("failed to create temp dir");
+        let path = dir.path().join("download.part");
+        fs::write(&path, b"hello world").expect("failed to write partial download");
+        download(&client().expect("failed to create client"), &format!("{}/asset", base_url), &path, |_, _| ()).expect("download failed");
+        assert_eq!(fs::read(&path).expect("failed to read download"), b"hello world");
+        let requests = server.join().expect("test server panicked");
+        assert_eq!(requests[0].header("range"), Some("bytes=11-"));
+    }
+
+    #[test]
+    fn write_atomic_replaces() {
+        let dir = tempfile::tempdir().expect("failed to create temp dir");
+        let path = dir.path().join("config.json");
+        fs::write(&path, "old").expect("failed to write file");
+        write_atomic(&path, "new").expect("failed to replace file");
+        assert_eq!(fs::read_to_string(&path).expect("failed to read file"), "new");
+        assert!(!dir.path().join("config.json.tmp").exists());
+    }
+}